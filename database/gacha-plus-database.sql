@@ -15,16 +15,30 @@ CREATE TABLE `freeoc` (
   `owner` bigint(20) UNSIGNED NOT NULL,
   `secretid` char(9) CHARACTER SET ascii COLLATE ascii_general_ci NOT NULL,
   `mycode` text NOT NULL,
+  `boost` tinyint(3) UNSIGNED NOT NULL DEFAULT 0,
+  `position` int(10) UNSIGNED NOT NULL DEFAULT 0,
   `createdate` timestamp NOT NULL DEFAULT current_timestamp(),
   `updatedate` timestamp NOT NULL DEFAULT current_timestamp() ON UPDATE current_timestamp()
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci ROW_FORMAT=COMPACT;
 
+CREATE TABLE `freeoc_user` (
+  `id` bigint(20) UNSIGNED NOT NULL,
+  `name` varchar(64) NOT NULL,
+  `link` varchar(512) NOT NULL DEFAULT '',
+  `regdate` timestamp NOT NULL DEFAULT current_timestamp()
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci ROW_FORMAT=COMPACT;
+
 CREATE TABLE `latestversion` (
   `id` int(10) UNSIGNED NOT NULL,
   `version` varchar(512) NOT NULL,
   `checksum` varchar(512) NOT NULL,
   `url` varchar(512) NOT NULL,
-  `regdate` timestamp NOT NULL DEFAULT current_timestamp()
+  `platform` varchar(7) CHARACTER SET armscii8 COLLATE armscii8_general_ci DEFAULT NULL,
+  `xbits` tinyint(3) UNSIGNED DEFAULT NULL,
+  `channel` varchar(16) CHARACTER SET ascii COLLATE ascii_general_ci NOT NULL DEFAULT 'stable',
+  `rollout` tinyint(3) UNSIGNED NOT NULL DEFAULT 100,
+  `regdate` timestamp NOT NULL DEFAULT current_timestamp(),
+  `rollbackdate` timestamp NULL DEFAULT NULL
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci ROW_FORMAT=COMPACT;
 
 CREATE TABLE `oc` (
@@ -49,6 +63,59 @@ PARTITION p8 ENGINE=InnoDB,
 PARTITION p9 ENGINE=InnoDB
 );
 
+CREATE TABLE `oc_moderation` (
+  `accountx` char(7) CHARACTER SET ascii COLLATE ascii_general_ci NOT NULL,
+  `status` tinyint(3) UNSIGNED NOT NULL,
+  `updatedate` timestamp NOT NULL DEFAULT current_timestamp() ON UPDATE current_timestamp()
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci ROW_FORMAT=COMPACT;
+
+CREATE TABLE `oc_moderation_log` (
+  `id` int(10) UNSIGNED NOT NULL,
+  `accountx` char(7) CHARACTER SET ascii COLLATE ascii_general_ci NOT NULL,
+  `action` tinyint(3) UNSIGNED NOT NULL,
+  `moderator` int(10) UNSIGNED NOT NULL,
+  `reports` int(10) UNSIGNED NOT NULL DEFAULT 0,
+  `regdate` timestamp NOT NULL DEFAULT current_timestamp()
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci ROW_FORMAT=COMPACT;
+
+CREATE TABLE `oc_of_the_day` (
+  `day` date NOT NULL,
+  `accountx` char(7) CHARACTER SET ascii COLLATE ascii_general_ci NOT NULL,
+  `cycle` int(10) UNSIGNED DEFAULT NULL,
+  `regdate` timestamp NOT NULL DEFAULT current_timestamp()
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci ROW_FORMAT=COMPACT;
+
+CREATE TABLE `oc_ranking` (
+  `accountx` char(7) CHARACTER SET ascii COLLATE ascii_general_ci NOT NULL,
+  `hour` int(10) UNSIGNED NOT NULL,
+  `imports` int(10) UNSIGNED NOT NULL DEFAULT 0
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci ROW_FORMAT=COMPACT;
+
+CREATE TABLE `oc_ranking_exclude` (
+  `accountx` char(7) CHARACTER SET ascii COLLATE ascii_general_ci NOT NULL,
+  `reason` tinyint(3) UNSIGNED NOT NULL,
+  `regdate` timestamp NOT NULL DEFAULT current_timestamp()
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci ROW_FORMAT=COMPACT;
+
+CREATE TABLE `oc_ranking_total` (
+  `accountx` char(7) CHARACTER SET ascii COLLATE ascii_general_ci NOT NULL,
+  `imports` bigint(20) UNSIGNED NOT NULL DEFAULT 0
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci ROW_FORMAT=COMPACT;
+
+CREATE TABLE `oc_report` (
+  `id` int(10) UNSIGNED NOT NULL,
+  `accountx` char(7) CHARACTER SET ascii COLLATE ascii_general_ci NOT NULL,
+  `reason` varchar(255) NOT NULL,
+  `reporter` int(10) UNSIGNED NOT NULL,
+  `regdate` timestamp NOT NULL DEFAULT current_timestamp()
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci ROW_FORMAT=COMPACT;
+
+CREATE TABLE `settings` (
+  `name` varchar(64) CHARACTER SET ascii COLLATE ascii_general_ci NOT NULL,
+  `value` varchar(512) NOT NULL,
+  `updatedate` timestamp NOT NULL DEFAULT current_timestamp() ON UPDATE current_timestamp()
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci ROW_FORMAT=COMPACT;
+
 CREATE TABLE `shortlog` (
   `id` int(10) UNSIGNED NOT NULL,
   `address` int(10) UNSIGNED NOT NULL,
@@ -87,12 +154,13 @@ INSERT INTO `shortlog_actions` (`id`, `name`) VALUES
 (4, 'WINAPPgetversion'),
 (5, 'ALLexport'),
 (6, 'ALLimport'),
-(7, 'startups');
+(7, 'startups'),
+(8, 'OCdaily');
 
 CREATE TABLE `startup_log` (
   `id` int(10) UNSIGNED NOT NULL,
   `platform` varchar(7) CHARACTER SET armscii8 COLLATE armscii8_general_ci NOT NULL,
-  `version` varchar(7) CHARACTER SET armscii8 COLLATE armscii8_general_ci NOT NULL,
+  `version` varchar(17) CHARACTER SET armscii8 COLLATE armscii8_general_ci NOT NULL,
   `xbits` tinyint(3) UNSIGNED NOT NULL,
   `regdate` timestamp NOT NULL DEFAULT current_timestamp()
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci ROW_FORMAT=COMPACT;
@@ -101,20 +169,72 @@ CREATE TABLE `transfer` (
   `accountx` int(10) UNSIGNED NOT NULL,
   `data` longtext CHARACTER SET utf8mb4 COLLATE utf8mb4_bin NOT NULL CHECK (json_valid(`data`)),
   `used` smallint(10) UNSIGNED NOT NULL DEFAULT 0,
+  `regdate` timestamp NOT NULL DEFAULT current_timestamp(),
+  `pin` varchar(255) CHARACTER SET ascii COLLATE ascii_general_ci DEFAULT NULL,
+  `key_id` varchar(32) CHARACTER SET ascii COLLATE ascii_general_ci DEFAULT NULL,
+  `owner_token` char(64) CHARACTER SET ascii COLLATE ascii_general_ci DEFAULT NULL
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci ROW_FORMAT=COMPRESSED;
+
+CREATE TABLE `transfer_reservation` (
+  `accountx` int(10) UNSIGNED NOT NULL,
+  `token` char(64) CHARACTER SET ascii COLLATE ascii_general_ci NOT NULL,
+  `regdate` timestamp NOT NULL DEFAULT current_timestamp()
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci;
+
+CREATE TABLE `transfer_snapshot` (
+  `id` bigint(20) UNSIGNED NOT NULL,
+  `accountx` int(10) UNSIGNED NOT NULL,
+  `data` longtext CHARACTER SET utf8mb4 COLLATE utf8mb4_bin NOT NULL CHECK (json_valid(`data`)),
+  `key_id` varchar(32) CHARACTER SET ascii COLLATE ascii_general_ci DEFAULT NULL,
   `regdate` timestamp NOT NULL DEFAULT current_timestamp()
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_general_ci ROW_FORMAT=COMPRESSED;
 
 
 ALTER TABLE `freeoc`
   ADD PRIMARY KEY (`accountx`),
-  ADD KEY `owner` (`owner`);
+  ADD KEY `owner` (`owner`),
+  ADD KEY `position` (`position`),
+  ADD KEY `updatedate` (`updatedate`);
 
-ALTER TABLE `latestversion`
+ALTER TABLE `freeoc_user`
   ADD PRIMARY KEY (`id`);
 
+ALTER TABLE `latestversion`
+  ADD PRIMARY KEY (`id`),
+  ADD KEY `target` (`platform`,`xbits`,`channel`);
+
 ALTER TABLE `oc`
   ADD PRIMARY KEY (`accountx`);
 
+ALTER TABLE `oc_moderation`
+  ADD PRIMARY KEY (`accountx`),
+  ADD KEY `status` (`status`);
+
+ALTER TABLE `oc_moderation_log`
+  ADD PRIMARY KEY (`id`),
+  ADD KEY `accountx` (`accountx`);
+
+ALTER TABLE `oc_of_the_day`
+  ADD PRIMARY KEY (`day`),
+  ADD KEY `cycle` (`cycle`);
+
+ALTER TABLE `oc_ranking`
+  ADD PRIMARY KEY (`accountx`,`hour`),
+  ADD KEY `hour` (`hour`);
+
+ALTER TABLE `oc_ranking_exclude`
+  ADD PRIMARY KEY (`accountx`,`reason`);
+
+ALTER TABLE `oc_ranking_total`
+  ADD PRIMARY KEY (`accountx`);
+
+ALTER TABLE `oc_report`
+  ADD PRIMARY KEY (`id`),
+  ADD UNIQUE KEY `accountx_reporter` (`accountx`,`reporter`);
+
+ALTER TABLE `settings`
+  ADD PRIMARY KEY (`name`);
+
 ALTER TABLE `shortlog`
   ADD PRIMARY KEY (`id`,`regdate`),
   ADD KEY `action` (`action`);
@@ -126,18 +246,42 @@ ALTER TABLE `startup_log`
   ADD PRIMARY KEY (`id`);
 
 ALTER TABLE `transfer`
-  ADD PRIMARY KEY (`accountx`);
+  ADD PRIMARY KEY (`accountx`),
+  ADD KEY `used` (`used`),
+  ADD KEY `regdate` (`regdate`),
+  ADD KEY `key_id` (`key_id`);
+
+ALTER TABLE `transfer_reservation`
+  ADD PRIMARY KEY (`accountx`),
+  ADD KEY `regdate` (`regdate`);
+
+ALTER TABLE `transfer_snapshot`
+  ADD PRIMARY KEY (`id`),
+  ADD KEY `accountx` (`accountx`),
+  ADD KEY `key_id` (`key_id`);
 
 
+ALTER TABLE `freeoc_user`
+  MODIFY `id` bigint(20) UNSIGNED NOT NULL AUTO_INCREMENT;
+
 ALTER TABLE `latestversion`
   MODIFY `id` int(10) UNSIGNED NOT NULL AUTO_INCREMENT;
 
+ALTER TABLE `oc_moderation_log`
+  MODIFY `id` int(10) UNSIGNED NOT NULL AUTO_INCREMENT;
+
+ALTER TABLE `oc_report`
+  MODIFY `id` int(10) UNSIGNED NOT NULL AUTO_INCREMENT;
+
 ALTER TABLE `shortlog`
   MODIFY `id` int(10) UNSIGNED NOT NULL AUTO_INCREMENT;
 
 ALTER TABLE `startup_log`
   MODIFY `id` int(10) UNSIGNED NOT NULL AUTO_INCREMENT;
 
+ALTER TABLE `transfer_snapshot`
+  MODIFY `id` bigint(20) UNSIGNED NOT NULL AUTO_INCREMENT;
+
 
 ALTER TABLE `freeoc`
   ADD CONSTRAINT `freeoc_ibfk_1` FOREIGN KEY (`owner`) REFERENCES `freeoc_user` (`id`) ON DELETE CASCADE ON UPDATE CASCADE;
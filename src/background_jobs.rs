@@ -2,7 +2,12 @@ use std::sync::Arc;
 
 use super::http_handler::AppState;
 mod clear_ratelimit_cache;
-mod random_character_cache;
+pub mod latest_version_cache;
+mod oc_ranking_snapshot;
+pub mod random_character_cache;
+mod settings_cache;
+mod transfer_cleanup;
+mod transfer_reencrypt;
 mod write_out_log;
 
 pub fn start(app_state: Arc<AppState>) {
@@ -13,4 +18,17 @@ pub fn start(app_state: Arc<AppState>) {
     tokio::spawn(clear_ratelimit_cache::cleanup_ratelimit_cache(
         app_state.clone(),
     ));
+    tokio::spawn(oc_ranking_snapshot::oc_ranking_snapshot_service(
+        app_state.clone(),
+    ));
+    tokio::spawn(settings_cache::settings_cache_service(app_state.clone()));
+    tokio::spawn(latest_version_cache::latest_version_cache_service(
+        app_state.clone(),
+    ));
+    tokio::spawn(transfer_cleanup::transfer_cleanup_service(
+        app_state.clone(),
+    ));
+    tokio::spawn(transfer_reencrypt::transfer_reencrypt_service(
+        app_state.clone(),
+    ));
 }
@@ -7,7 +7,7 @@ use chrono::Utc;
 use inline_colorization::*;
 use tokio::time::sleep;
 
-use crate::http_handler::AppState;
+use crate::{http_handler::AppState, random_selector::SelectorConfig};
 
 pub async fn cleanup_ratelimit_cache(app_state: Arc<AppState>) {
     loop {
@@ -21,8 +21,34 @@ pub async fn cleanup_ratelimit_cache(app_state: Arc<AppState>) {
             cache.retain(|_, &mut timestamp| timestamp > now);
             count_after += cache.len();
         }
+        let config = SelectorConfig::from_settings(&app_state.settings).await;
+        let history_removed = app_state
+            .random_selector
+            .cleanup(config.history_duration)
+            .await;
+        let pin_attempts_removed = app_state.pin_attempts.cleanup().await;
         let delay_in_ms = now.elapsed().as_micros() as f64 / 1000f64;
 
+        if history_removed > 0 {
+            println!(
+                "{}{}\tRateLimitCleaner: {} random history removed!\tDelay: {:.3} ms{}",
+                color_bright_black,
+                Utc::now().format("[%H:%M:%S]"),
+                history_removed,
+                delay_in_ms,
+                color_white,
+            );
+        }
+        if pin_attempts_removed > 0 {
+            println!(
+                "{}{}\tRateLimitCleaner: {} PIN attempt counter removed!\tDelay: {:.3} ms{}",
+                color_bright_black,
+                Utc::now().format("[%H:%M:%S]"),
+                pin_attempts_removed,
+                delay_in_ms,
+                color_white,
+            );
+        }
         if count_before - count_after > 0 {
             println!(
                 "{}{}\tRateLimitCleaner: {} ip removed!\tDelay: {:.3} ms{}",
@@ -0,0 +1,64 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use chrono::Utc;
+use inline_colorization::*;
+use tokio::time::sleep;
+
+use crate::{gachaplus_database::latestversion_table::LatestVersionRow, http_handler::AppState};
+
+pub async fn latest_version_cache_service(app_state: Arc<AppState>) {
+    let mut last_len = 0;
+    loop {
+        let now = Instant::now();
+        match refresh_latest_versions(&app_state).await {
+            Ok(len) => {
+                let delay_in_ms = now.elapsed().as_micros() as f64 / 1000f64;
+                if len != last_len {
+                    println!(
+                        "{}{}\tLatestVersion: {} release loaded!\tDelay: {:.3} ms{}",
+                        color_bright_black,
+                        Utc::now().format("[%H:%M:%S]"),
+                        len,
+                        delay_in_ms,
+                        color_white,
+                    );
+                    last_len = len;
+                }
+            }
+            // the last loaded releases are still served
+            Err(error) => println!(
+                "{}{}\tLatestVersion: Error at loading the releases: {:?}{}",
+                color_yellow,
+                Utc::now().format("[%H:%M:%S]"),
+                error,
+                color_white,
+            ),
+        }
+
+        sleep(Duration::from_secs(60)).await;
+    }
+}
+
+/// Loading the releases into `latest_versions`, returning their count.
+pub async fn refresh_latest_versions(app_state: &AppState) -> Result<usize, sqlx::Error> {
+    let rows = app_state
+        .database
+        .latestversion_table
+        .get_versions()
+        .await?;
+    let len = rows.len();
+    app_state.latest_versions.store(rows).await;
+    Ok(len)
+}
+
+/// The cached releases, loading them if the job hasn't yet.
+pub async fn get_latest_versions(app_state: &AppState) -> Option<Arc<Vec<LatestVersionRow>>> {
+    if let Some(rows) = app_state.latest_versions.load().await {
+        return Some(rows);
+    }
+    refresh_latest_versions(app_state).await.ok()?;
+    app_state.latest_versions.load().await
+}
@@ -0,0 +1,112 @@
+use std::{
+    collections::HashSet,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use chrono::Utc;
+use inline_colorization::*;
+use tokio::time::sleep;
+
+use crate::{
+    http_handler::AppState,
+    oc_ranking::{self, ALL_TIME_KEPT, KEPT_HOURS},
+};
+
+pub async fn oc_ranking_snapshot_service(app_state: Arc<AppState>) {
+    let mut compacted_hour = 0;
+    loop {
+        let now = Instant::now();
+        let hour = oc_ranking::current_hour();
+        let rows = app_state.oc_ranking.take_snapshot_at(hour).await;
+        let rows_len = rows.len();
+        let mut failed_rows = Vec::new();
+        for row in rows {
+            let result = app_state
+                .database
+                .oc_ranking_table
+                .insert_or_update(&row)
+                .await;
+            if let Err(error) = result {
+                println!(
+                    "{}{}\tRanking: Error at saving the snapshot: {:?}{}",
+                    color_yellow,
+                    Utc::now().format("[%H:%M:%S]"),
+                    error,
+                    color_white,
+                );
+                failed_rows.push(row);
+            }
+        }
+        let failed_len = failed_rows.len();
+        if !failed_rows.is_empty() {
+            app_state.oc_ranking.restore_dirty(failed_rows).await;
+        }
+
+        // once an hour, after the rows of the dropped buckets are written
+        if compacted_hour != hour {
+            let before_hour = hour.saturating_sub(KEPT_HOURS);
+            match app_state
+                .database
+                .oc_ranking_table
+                .compact(before_hour)
+                .await
+            {
+                Ok(deleted) => {
+                    compacted_hour = hour;
+                    println!(
+                        "{}{}\tRanking: {} hourly row compacted!{}",
+                        color_bright_black,
+                        Utc::now().format("[%H:%M:%S]"),
+                        deleted,
+                        color_white,
+                    );
+                }
+                Err(error) => println!(
+                    "{}{}\tRanking: Error at compacting the hourly rows: {:?}{}",
+                    color_yellow,
+                    Utc::now().format("[%H:%M:%S]"),
+                    error,
+                    color_white,
+                ),
+            }
+        }
+
+        match app_state
+            .database
+            .oc_ranking_table
+            .get_all_time_top(ALL_TIME_KEPT)
+            .await
+        {
+            Ok(all_time) => app_state.oc_ranking.set_all_time(all_time).await,
+            Err(error) => println!(
+                "{}{}\tRanking: Error at loading the all-time top: {:?}{}",
+                color_yellow,
+                Utc::now().format("[%H:%M:%S]"),
+                error,
+                color_white,
+            ),
+        }
+
+        if let Ok(excluded) = app_state.database.oc_ranking_table.get_excluded().await {
+            app_state
+                .oc_ranking
+                .set_excluded(excluded.into_iter().collect::<HashSet<String>>())
+                .await;
+        }
+        let delay_in_ms = now.elapsed().as_micros() as f64 / 1000f64;
+
+        if rows_len > 0 {
+            println!(
+                "{}{}\tRanking: {} ranking row saved!\tDelay: {:.3} ms{}",
+                color_bright_black,
+                Utc::now().format("[%H:%M:%S]"),
+                rows_len - failed_len,
+                delay_in_ms,
+                color_white,
+            );
+        }
+
+        sleep(Duration::from_secs(60)).await;
+    }
+}
@@ -1,4 +1,5 @@
 use std::{
+    collections::HashSet,
     sync::Arc,
     time::{Duration, Instant},
 };
@@ -7,33 +8,107 @@ use chrono::Utc;
 use inline_colorization::*;
 use tokio::time::sleep;
 
-use crate::http_handler::AppState;
+use crate::{http_handler::AppState, oc_ranking::RankingWindow};
+
+/// Default time between the full reloads *(can be changed with the `freeoc_full_refresh_minutes` setting)*
+const DEFAULT_FULL_REFRESH_MINUTES: u64 = 15;
 
 pub async fn random_character_cache_service(app_state: Arc<AppState>) {
     let mut lastlen = 0;
+    let mut last_full_refresh: Option<Instant> = None;
     loop {
-        let ocs_res = app_state.database.oc_random_table.get_ocs().await;
-        if let Ok(ocs) = ocs_res {
-            let now = Instant::now();
-            let ocs_len = ocs.len();
-            {
-                let mut writer = app_state.oc_chache.write().await;
-                *writer = ocs;
+        let now = Instant::now();
+        let full_refresh_interval = Duration::from_secs(
+            app_state
+                .settings
+                .get("freeoc_full_refresh_minutes", DEFAULT_FULL_REFRESH_MINUTES)
+                .await
+                * 60,
+        );
+        let res = match last_full_refresh {
+            Some(last) if last.elapsed() < full_refresh_interval => {
+                refresh_free_oc_cache_delta(&app_state).await
+            }
+            _ => {
+                let res = refresh_free_oc_cache(&app_state).await;
+                if res.is_ok() {
+                    last_full_refresh = Some(now);
+                }
+                res
             }
-            let delay_in_ms = now.elapsed().as_micros() as f64 / 1000f64;
-
-            if lastlen != ocs_len {
-                println!(
-                    "{}{}\tRandom character: {} character in the random character cache 🙆\tDelay: {:.3} ms{}",
-                    color_bright_black,
-                    Utc::now().format("[%H:%M:%S]"),
-                    ocs_len,
-                    delay_in_ms,
-                    color_white
-                );
-                lastlen = ocs_len;
+        };
+
+        match res {
+            Ok(ocs_len) => {
+                app_state
+                    .random_selector
+                    .set_popularity(app_state.oc_ranking.imports(RankingWindow::Week).await)
+                    .await;
+                let delay_in_ms = now.elapsed().as_micros() as f64 / 1000f64;
+
+                if lastlen != ocs_len {
+                    println!(
+                        "{}{}\tRandom character: {} character in the random character cache 🙆\tDelay: {:.3} ms{}",
+                        color_bright_black,
+                        Utc::now().format("[%H:%M:%S]"),
+                        ocs_len,
+                        delay_in_ms,
+                        color_white
+                    );
+                    lastlen = ocs_len;
+                }
             }
+            Err(error) => println!(
+                "{}{}\tRandom character: Error at refreshing the random character cache: {:?}{}",
+                color_yellow,
+                Utc::now().format("[%H:%M:%S]"),
+                error,
+                color_white,
+            ),
         }
         sleep(Duration::from_secs(60)).await;
     }
 }
+
+/// Reloading every free OC into `oc_chache` without the hidden ones, returning the count of them.
+///
+/// *(Also called after admin and moderation changes, so they don't wait for the next refresh.)*
+pub async fn refresh_free_oc_cache(app_state: &AppState) -> Result<usize, sqlx::Error> {
+    let mut sync = app_state.oc_chache.lock_sync().await;
+    let ocs = app_state.database.oc_random_table.get_ocs().await?;
+    let hidden = get_hidden(app_state).await?;
+    let snapshot = sync.full(ocs, hidden);
+    Ok(app_state.oc_chache.store(snapshot).await)
+}
+
+/// Merging only the free OCs changed since the last sync into `oc_chache`, returning the count of them.
+///
+/// *(The deleted ones are only dropped by the full reload.)*
+async fn refresh_free_oc_cache_delta(app_state: &AppState) -> Result<usize, sqlx::Error> {
+    let mut sync = app_state.oc_chache.lock_sync().await;
+    let changed = match sync.last_update() {
+        Some(since) => {
+            app_state
+                .database
+                .oc_random_table
+                .get_ocs_updated_since(since)
+                .await?
+        }
+        None => app_state.database.oc_random_table.get_ocs().await?,
+    };
+    let hidden = get_hidden(app_state).await?;
+    match sync.delta(changed, hidden) {
+        Some(snapshot) => Ok(app_state.oc_chache.store(snapshot).await),
+        None => Ok(app_state.oc_chache.load().await.len()),
+    }
+}
+
+async fn get_hidden(app_state: &AppState) -> Result<HashSet<String>, sqlx::Error> {
+    Ok(app_state
+        .database
+        .oc_moderation_table
+        .get_hidden()
+        .await?
+        .into_iter()
+        .collect())
+}
@@ -0,0 +1,60 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use chrono::Utc;
+use inline_colorization::*;
+use tokio::time::sleep;
+
+use crate::http_handler::AppState;
+
+pub async fn settings_cache_service(app_state: Arc<AppState>) {
+    let mut last_settings = app_state.settings.all().await;
+    loop {
+        sleep(Duration::from_secs(30)).await;
+
+        let now = Instant::now();
+        match app_state.database.settings_table.get_all().await {
+            Ok(values) => {
+                app_state.settings.replace(values).await;
+                app_state
+                    .database
+                    .oc_table
+                    .cache
+                    .configure_from_settings(&app_state.settings)
+                    .await;
+                let settings = app_state.settings.all().await;
+                let delay_in_ms = now.elapsed().as_micros() as f64 / 1000f64;
+
+                if settings != last_settings {
+                    println!(
+                        "{}{}\tSettings: {} setting loaded ⚙️\tDelay: {:.3} ms{}",
+                        color_bright_black,
+                        Utc::now().format("[%H:%M:%S]"),
+                        settings.len(),
+                        delay_in_ms,
+                        color_white
+                    );
+                    last_settings = settings;
+                    for error in app_state.settings.invalid().await {
+                        println!(
+                            "{}{}\tSettings: {}, the default is used{}",
+                            color_yellow,
+                            Utc::now().format("[%H:%M:%S]"),
+                            error,
+                            color_white
+                        );
+                    }
+                }
+            }
+            Err(error) => println!(
+                "{}{}\tSettings: Error at loading the settings: {:?}{}",
+                color_yellow,
+                Utc::now().format("[%H:%M:%S]"),
+                error,
+                color_white,
+            ),
+        }
+    }
+}
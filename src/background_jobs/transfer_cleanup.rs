@@ -0,0 +1,100 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use chrono::{TimeDelta, Utc};
+use inline_colorization::*;
+use tokio::time::sleep;
+
+use crate::{
+    http_handler::AppState,
+    transfer_expiry::{TransferExpiry, MAX_DAYS},
+};
+
+/// Removing the expired transfer rows *(with their snapshots)* every hour.
+pub async fn transfer_cleanup_service(app_state: Arc<AppState>) {
+    loop {
+        sleep(Duration::from_secs(60 * 60)).await;
+
+        let now = Instant::now();
+        let expiry = TransferExpiry::from_settings(&app_state.settings).await;
+        let table = &app_state.database.tranfer_datas_table;
+        let mut removed = 0;
+        let mut snapshots_removed = 0;
+        if let Some(max_uses) = expiry.max_uses {
+            match table.delete_used(max_uses).await {
+                Ok(result) => removed += result.rows_affected(),
+                Err(error) => print_error(error),
+            }
+        }
+        if let Some(oldest_allowed) = expiry.oldest_allowed(Utc::now()) {
+            match table.delete_older(oldest_allowed).await {
+                Ok(result) => removed += result.rows_affected(),
+                Err(error) => print_error(error),
+            }
+        }
+        // the earlier datas of the removed transfers
+        match table.delete_orphan_snapshots().await {
+            Ok(result) => snapshots_removed += result.rows_affected(),
+            Err(error) => print_error(error),
+        }
+        // the allocated numbers never uploaded
+        let mut reservations_removed = 0;
+        let reservation_days: i64 = app_state
+            .settings
+            .get("transfer_reservation_days", 7)
+            .await
+            .clamp(1, MAX_DAYS);
+        let oldest_reservation = TimeDelta::try_days(reservation_days)
+            .and_then(|max_age| Utc::now().checked_sub_signed(max_age));
+        if let Some(oldest_reservation) = oldest_reservation {
+            match table.delete_reservations_older(oldest_reservation).await {
+                Ok(result) => reservations_removed += result.rows_affected(),
+                Err(error) => print_error(error),
+            }
+        }
+        let delay_in_ms = now.elapsed().as_micros() as f64 / 1000f64;
+
+        if removed > 0 {
+            println!(
+                "{}{}\tTransferCleaner: {} expired transfer removed!\tDelay: {:.3} ms{}",
+                color_bright_black,
+                Utc::now().format("[%H:%M:%S]"),
+                removed,
+                delay_in_ms,
+                color_white,
+            );
+        }
+        if snapshots_removed > 0 {
+            println!(
+                "{}{}\tTransferCleaner: {} snapshot of removed transfers removed!\tDelay: {:.3} ms{}",
+                color_bright_black,
+                Utc::now().format("[%H:%M:%S]"),
+                snapshots_removed,
+                delay_in_ms,
+                color_white,
+            );
+        }
+        if reservations_removed > 0 {
+            println!(
+                "{}{}\tTransferCleaner: {} unused reservation removed!\tDelay: {:.3} ms{}",
+                color_bright_black,
+                Utc::now().format("[%H:%M:%S]"),
+                reservations_removed,
+                delay_in_ms,
+                color_white,
+            );
+        }
+    }
+}
+
+fn print_error(error: sqlx::Error) {
+    println!(
+        "{}{}\tTransferCleaner: Error at removing the expired transfers: {:?}{}",
+        color_yellow,
+        Utc::now().format("[%H:%M:%S]"),
+        error,
+        color_white,
+    );
+}
@@ -0,0 +1,125 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use chrono::Utc;
+use inline_colorization::*;
+use tokio::time::sleep;
+
+use crate::{gachaplus_database::tranfer_datas_table::StoredTransferData, http_handler::AppState};
+
+/// Rows encrypted again in one round
+const BATCH_SIZE: u32 = 100;
+
+/// Migrating the plaintext rows and the rows of the old keys *(snapshots too)* to the active key.
+///
+/// *(The rows are paged by their key, so the ones failing to decrypt are skipped until the next pass.)*
+pub async fn transfer_reencrypt_service(app_state: Arc<AppState>) {
+    let table = &app_state.database.tranfer_datas_table;
+    let Some(key_id) = table.crypto.active_key_id().map(|id| id.to_owned()) else {
+        println!(
+            "{}{}\tTransferReencrypt: No encryption key in `TRANSFER_KEYS`, the transfers stay in plaintext!{}",
+            color_yellow,
+            Utc::now().format("[%H:%M:%S]"),
+            color_white,
+        );
+        return;
+    };
+    // the last row of the pass per table, `None` when the table is done
+    let mut after_accountx = Some(0);
+    let mut after_id = Some(0);
+    loop {
+        let now = Instant::now();
+        let mut migrated = 0;
+        let mut failed = 0;
+
+        let rows = match after_accountx {
+            Some(after) => table.get_unmigrated(&key_id, after, BATCH_SIZE).await,
+            None => Ok(Vec::new()),
+        };
+        let snapshots = match after_id {
+            Some(after) => {
+                table
+                    .get_unmigrated_snapshots(&key_id, after, BATCH_SIZE)
+                    .await
+            }
+            None => Ok(Vec::new()),
+        };
+        match (rows, snapshots) {
+            (Ok(rows), Ok(snapshots)) => {
+                if after_accountx.is_some() {
+                    after_accountx = next_cursor(&rows, |row| row.accountx);
+                }
+                if after_id.is_some() {
+                    after_id = next_cursor(&snapshots, |row| row.id.unwrap_or_default());
+                }
+                for row in rows.iter().chain(snapshots.iter()) {
+                    match table.reencrypt(row).await {
+                        Ok(true) => migrated += 1,
+                        // changed meanwhile, the next pass tries again
+                        Ok(false) => (),
+                        Err(error) => {
+                            failed += 1;
+                            println!(
+                                "{}{}\tTransferReencrypt: Error at re-encrypting '{}': {:?}{}",
+                                color_yellow,
+                                Utc::now().format("[%H:%M:%S]"),
+                                row.accountx,
+                                error,
+                                color_white,
+                            );
+                        }
+                    }
+                }
+            }
+            (Err(error), _) | (_, Err(error)) => {
+                // the pass starts again
+                after_accountx = None;
+                after_id = None;
+                println!(
+                    "{}{}\tTransferReencrypt: Error at loading the rows: {:?}{}",
+                    color_yellow,
+                    Utc::now().format("[%H:%M:%S]"),
+                    error,
+                    color_white,
+                );
+            }
+        }
+        let delay_in_ms = now.elapsed().as_micros() as f64 / 1000f64;
+
+        if migrated > 0 || failed > 0 {
+            println!(
+                "{}{}\tTransferReencrypt: {} transfer encrypted with '{}', {} skipped!\tDelay: {:.3} ms{}",
+                color_bright_black,
+                Utc::now().format("[%H:%M:%S]"),
+                migrated,
+                key_id,
+                failed,
+                delay_in_ms,
+                color_white,
+            );
+        }
+
+        let wait = if after_accountx.is_some() || after_id.is_some() {
+            1
+        } else {
+            // the pass is over, the next one starts from the beginning
+            after_accountx = Some(0);
+            after_id = Some(0);
+            10 * 60
+        };
+        sleep(Duration::from_secs(wait)).await;
+    }
+}
+
+/// The key of the last row of a full batch, `None` if it was the last batch.
+fn next_cursor<T>(
+    rows: &[StoredTransferData],
+    key: impl Fn(&StoredTransferData) -> T,
+) -> Option<T> {
+    if rows.len() < BATCH_SIZE as usize {
+        return None;
+    }
+    rows.last().map(key)
+}
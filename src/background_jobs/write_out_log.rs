@@ -34,7 +34,7 @@ pub async fn write_out_log_service(app_state: Arc<AppState>) {
             }
         }
 
-        if current_logs.len() > 0 {
+        if !current_logs.is_empty() {
             println!(
                 "{}{}\tLogging: {} log row added!\tAVG delay: {:.3} ms\tAll delay: {:.3} ms{}",
                 color_bright_black,
@@ -50,7 +50,7 @@ pub async fn write_out_log_service(app_state: Arc<AppState>) {
     }
 }
 
-fn median(numbers: &mut Vec<f64>) -> Option<f64> {
+fn median(numbers: &mut [f64]) -> Option<f64> {
     let len = numbers.len();
     numbers.sort_by(|a, b| a.partial_cmp(b).unwrap());
 
@@ -58,7 +58,7 @@ fn median(numbers: &mut Vec<f64>) -> Option<f64> {
         0 => None,
         _ => {
             let mid = len / 2;
-            if len % 2 == 0 {
+            if len.is_multiple_of(2) {
                 Some((numbers[mid - 1] + numbers[mid]) / 2.0)
             } else {
                 Some(numbers[mid])
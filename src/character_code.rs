@@ -46,7 +46,7 @@ impl CharacterCode {
             };
         }
         for param in params[279..].iter() {
-            match Color::new_from_hex(&param) {
+            match Color::new_from_hex(param) {
                 Ok(color) => colors.push(color),
                 Err(err) => return Err(err),
             };
@@ -0,0 +1,110 @@
+use std::{fmt, str::FromStr};
+
+use crate::settings::Settings;
+
+/// Header of the newer clients with their version
+///
+/// *(The released clients don't send it, their version is only known from the form of `startup.php`.)*
+pub const VERSION_HEADER: &str = "X-Client-Version";
+
+/// Semantic version of the client *(`major.minor.patch`, the missing parts are `0`)*.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ClientVersion {
+    pub major: u16,
+    pub minor: u16,
+    pub patch: u16,
+}
+
+impl FromStr for ClientVersion {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim();
+        let text = text.strip_prefix(['v', 'V']).unwrap_or(text);
+        let parts: Vec<&str> = text.split('.').collect();
+        if parts.is_empty() || parts.len() > 3 {
+            return Err(format!("Invalid version: '{text}'"));
+        }
+        let mut numbers = [0u16; 3];
+        for (number, part) in numbers.iter_mut().zip(parts) {
+            *number = part
+                .parse()
+                .map_err(|_| format!("Invalid version: '{text}'"))?;
+        }
+        Ok(Self {
+            major: numbers[0],
+            minor: numbers[1],
+            patch: numbers[2],
+        })
+    }
+}
+
+impl fmt::Display for ClientVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// The minimum version for the route: `min_client_version:<path>`, or `min_client_version` for every route.
+///
+/// *(Enforced on `startup.php` for every client, on the other routes only for the clients sending [`VERSION_HEADER`].)*
+pub async fn min_version(settings: &Settings, path: &str) -> Option<ClientVersion> {
+    let route = settings
+        .get(&format!("min_client_version:{path}"), String::new())
+        .await;
+    let global = settings.get("min_client_version", String::new()).await;
+    [route, global]
+        .iter()
+        .find(|value| !value.trim().is_empty())
+        .and_then(|value| value.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn client_version_test() {
+        let version: ClientVersion = "1.2.3".parse().unwrap();
+        assert_eq!(version.to_string(), "1.2.3");
+        assert_eq!(
+            "v1.2".parse::<ClientVersion>().unwrap().to_string(),
+            "1.2.0"
+        );
+        assert_eq!("2".parse::<ClientVersion>().unwrap().to_string(), "2.0.0");
+        assert!("1.2.3.4".parse::<ClientVersion>().is_err());
+        assert!("1.a".parse::<ClientVersion>().is_err());
+        assert!("".parse::<ClientVersion>().is_err());
+
+        assert!("1.10.0".parse::<ClientVersion>().unwrap() > version);
+        assert!("1.2.10".parse::<ClientVersion>().unwrap() > version);
+        assert!("0.9.9".parse::<ClientVersion>().unwrap() < version);
+    }
+
+    #[tokio::test]
+    async fn min_version_test() {
+        let settings = Settings::default();
+        assert_eq!(
+            min_version(&settings, "/GPscripts/club_export.php").await,
+            None
+        );
+        settings
+            .replace(HashMap::from([
+                ("min_client_version".to_owned(), "1.0.0".to_owned()),
+                (
+                    "min_client_version:/GPscripts/club_export.php".to_owned(),
+                    "1.2".to_owned(),
+                ),
+            ]))
+            .await;
+        assert_eq!(
+            min_version(&settings, "/GPscripts/club_export.php").await,
+            Some("1.2.0".parse().unwrap())
+        );
+        assert_eq!(
+            min_version(&settings, "/GPscripts/club_import.php").await,
+            Some("1.0.0".parse().unwrap())
+        );
+    }
+}
@@ -0,0 +1,276 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use chrono::{DateTime, Utc};
+use tokio::sync::{Mutex, MutexGuard, RwLock};
+
+use crate::gachaplus_database::free_oc_table::FreeOc;
+
+/// One loaded state of the free OC pool, with an `accountx` index.
+#[derive(Default)]
+pub struct FreeOcSnapshot {
+    ocs: Vec<FreeOc>,
+    /// `accountx` => index in `ocs`
+    index: HashMap<String, usize>,
+    /// Hidden by the moderation, out of the pool but still importable
+    hidden: HashMap<String, FreeOc>,
+}
+
+impl FreeOcSnapshot {
+    pub fn new(ocs: Vec<FreeOc>, hidden: Vec<FreeOc>) -> Self {
+        let index = ocs
+            .iter()
+            .enumerate()
+            .map(|(index, oc)| (oc.accountx.to_owned(), index))
+            .collect();
+        let hidden = hidden
+            .into_iter()
+            .map(|oc| (oc.accountx.to_owned(), oc))
+            .collect();
+        Self { ocs, index, hidden }
+    }
+
+    /// The free OCs in the curated order.
+    pub fn ocs(&self) -> &[FreeOc] {
+        &self.ocs
+    }
+
+    /// The visible free OC.
+    pub fn get(&self, accountx: &str) -> Option<&FreeOc> {
+        self.ocs.get(*self.index.get(accountx)?)
+    }
+
+    /// The free OC, also if it's hidden *(for importing and reporting by `accountx`)*.
+    pub fn get_including_hidden(&self, accountx: &str) -> Option<&FreeOc> {
+        self.get(accountx).or_else(|| self.hidden.get(accountx))
+    }
+
+    pub fn len(&self) -> usize {
+        self.ocs.len()
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct SyncInfo {
+    /// The newest `updatedate` seen, the next delta starts from here
+    pub last_update: Option<DateTime<Utc>>,
+    pub last_sync: Option<DateTime<Utc>>,
+    /// Rows fetched by the last delta sync
+    pub last_sync_rows: usize,
+    pub last_full_sync: Option<DateTime<Utc>>,
+    /// Rows fetched by the last full sync
+    pub last_full_sync_rows: usize,
+}
+
+/// Every row of the `freeoc` table *(including the hidden ones)*, kept to merge the deltas into.
+#[derive(Default)]
+pub struct FreeOcSync {
+    rows: HashMap<String, FreeOc>,
+    hidden: HashSet<String>,
+    info: SyncInfo,
+}
+
+impl FreeOcSync {
+    pub fn last_update(&self) -> Option<DateTime<Utc>> {
+        self.info.last_update
+    }
+
+    /// Replacing every row, which also drops the deleted ones.
+    pub fn full(&mut self, ocs: Vec<FreeOc>, hidden: HashSet<String>) -> FreeOcSnapshot {
+        self.info.last_full_sync = Some(Utc::now());
+        self.info.last_full_sync_rows = ocs.len();
+        self.info.last_update = ocs.iter().map(|oc| oc.updatedate).max();
+        self.rows = ocs
+            .into_iter()
+            .map(|oc| (oc.accountx.to_owned(), oc))
+            .collect();
+        self.hidden = hidden;
+        self.build()
+    }
+
+    /// Merging the changed rows, returning the next snapshot if anything changed.
+    pub fn delta(
+        &mut self,
+        mut changed: Vec<FreeOc>,
+        hidden: HashSet<String>,
+    ) -> Option<FreeOcSnapshot> {
+        self.info.last_sync = Some(Utc::now());
+        self.info.last_sync_rows = changed.len();
+        // the rows of the last second are fetched again
+        changed.retain(|oc| self.rows.get(&oc.accountx) != Some(oc));
+        if changed.is_empty() && hidden == self.hidden {
+            return None;
+        }
+        for oc in changed {
+            if self
+                .info
+                .last_update
+                .is_none_or(|last| oc.updatedate > last)
+            {
+                self.info.last_update = Some(oc.updatedate);
+            }
+            self.rows.insert(oc.accountx.to_owned(), oc);
+        }
+        self.hidden = hidden;
+        Some(self.build())
+    }
+
+    /// The visible OCs in the same order as `FreeOcTable::get_ocs`, and the hidden ones aside.
+    fn build(&self) -> FreeOcSnapshot {
+        let (mut ocs, hidden): (Vec<FreeOc>, Vec<FreeOc>) = self
+            .rows
+            .values()
+            .cloned()
+            .partition(|oc| !self.hidden.contains(&oc.accountx));
+        ocs.sort_by(|a, b| {
+            a.position
+                .cmp(&b.position)
+                .then_with(|| a.accountx.cmp(&b.accountx))
+        });
+        FreeOcSnapshot::new(ocs, hidden)
+    }
+}
+
+/// Free OC pool, replaced as a whole by the refresh job.
+///
+/// *(The lock is only held while cloning or swapping the `Arc`, so the readers never wait for a rebuild.)*
+#[derive(Default)]
+pub struct FreeOcCache {
+    current: RwLock<Arc<FreeOcSnapshot>>,
+    sync: Mutex<FreeOcSync>,
+}
+
+impl FreeOcCache {
+    pub async fn load(&self) -> Arc<FreeOcSnapshot> {
+        self.current.read().await.clone()
+    }
+
+    /// Locking the sync state, held while fetching, so a full and a delta refresh can't interleave.
+    pub async fn lock_sync(&self) -> MutexGuard<'_, FreeOcSync> {
+        self.sync.lock().await
+    }
+
+    /// Swapping in the next snapshot, returning the count of the OCs.
+    pub async fn store(&self, snapshot: FreeOcSnapshot) -> usize {
+        let ocs_len = snapshot.len();
+        *self.current.write().await = Arc::new(snapshot);
+        ocs_len
+    }
+
+    pub async fn sync_info(&self) -> SyncInfo {
+        self.sync.lock().await.info.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeDelta;
+
+    fn free_oc(accountx: &str, mycode: &str) -> FreeOc {
+        FreeOc {
+            accountx: accountx.to_owned(),
+            owner: 0,
+            secretid: "SECRETID0".to_owned(),
+            mycode: mycode.to_owned(),
+            boost: 0,
+            position: 0,
+            createdate: Utc::now(),
+            updatedate: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn free_oc_cache_test() {
+        let cache = FreeOcCache::default();
+        assert!(cache.load().await.get("AAAAAAA").is_none());
+
+        let ocs_len = cache
+            .store(FreeOcSnapshot::new(
+                vec![free_oc("AAAAAAA", "first"), free_oc("BBBBBBB", "second")],
+                Vec::new(),
+            ))
+            .await;
+        assert_eq!(ocs_len, 2);
+
+        let old = cache.load().await;
+        assert_eq!(old.get("BBBBBBB").unwrap().mycode, "second");
+        assert_eq!(old.ocs()[0].accountx, "AAAAAAA");
+
+        // the readers keep their snapshot during the swap
+        cache
+            .store(FreeOcSnapshot::new(
+                vec![free_oc("CCCCCCC", "third")],
+                Vec::new(),
+            ))
+            .await;
+        assert_eq!(old.len(), 2);
+        let new = cache.load().await;
+        assert!(new.get("AAAAAAA").is_none());
+        assert_eq!(new.get("CCCCCCC").unwrap().mycode, "third");
+    }
+
+    #[test]
+    fn free_oc_sync_test() {
+        let mut sync = FreeOcSync::default();
+        let mut old = free_oc("BBBBBBB", "old");
+        old.updatedate -= TimeDelta::hours(1);
+        let snapshot = sync.full(vec![free_oc("AAAAAAA", "first"), old], HashSet::new());
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(sync.info.last_full_sync_rows, 2);
+        let last_update = sync.last_update().unwrap();
+
+        assert!(sync.delta(Vec::new(), HashSet::new()).is_none());
+        let unchanged = snapshot.get("AAAAAAA").unwrap().clone();
+        assert!(sync.delta(vec![unchanged], HashSet::new()).is_none());
+
+        // an edited row and a new one, ordered by `position`
+        let mut edited = free_oc("BBBBBBB", "new");
+        edited.updatedate = last_update + TimeDelta::seconds(10);
+        let mut new = free_oc("CCCCCCC", "third");
+        new.position = 1;
+        let snapshot = sync.delta(vec![edited, new], HashSet::new()).unwrap();
+        assert_eq!(snapshot.len(), 3);
+        assert_eq!(snapshot.get("BBBBBBB").unwrap().mycode, "new");
+        assert_eq!(snapshot.ocs()[2].accountx, "CCCCCCC");
+        assert_eq!(
+            sync.last_update(),
+            Some(last_update + TimeDelta::seconds(10))
+        );
+
+        // a changed hidden set is applied without changed rows
+        let hidden = HashSet::from(["AAAAAAA".to_owned()]);
+        let snapshot = sync.delta(Vec::new(), hidden.clone()).unwrap();
+        assert!(snapshot.get("AAAAAAA").is_none());
+
+        // only the full sync drops the deleted rows
+        let snapshot = sync.full(vec![free_oc("CCCCCCC", "third")], hidden);
+        assert_eq!(snapshot.len(), 1);
+    }
+
+    #[test]
+    fn free_oc_hidden_test() {
+        let mut sync = FreeOcSync::default();
+        let ocs = vec![free_oc("AAAAAAA", "first"), free_oc("BBBBBBB", "second")];
+        let snapshot = sync.full(ocs.clone(), HashSet::new());
+        assert!(snapshot.get("AAAAAAA").is_some());
+
+        // hidden after the reports: out of the pool, but still importable and reportable
+        let hidden = HashSet::from(["AAAAAAA".to_owned()]);
+        let snapshot = sync.full(ocs, hidden);
+        assert_eq!(snapshot.len(), 1);
+        assert!(snapshot.get("AAAAAAA").is_none());
+        assert!(snapshot.ocs().iter().all(|oc| oc.accountx != "AAAAAAA"));
+        assert_eq!(
+            snapshot.get_including_hidden("AAAAAAA").unwrap().mycode,
+            "first"
+        );
+        assert_eq!(
+            snapshot.get_including_hidden("BBBBBBB").unwrap().mycode,
+            "second"
+        );
+        assert!(snapshot.get_including_hidden("CCCCCCC").is_none());
+    }
+}
@@ -4,9 +4,15 @@ use chrono::Utc;
 use inline_colorization::*;
 use sqlx::mysql::MySqlPoolOptions;
 
+use crate::transfer_crypto::TransferCrypto;
+
 pub mod free_oc_table;
 pub mod latestversion_table;
+pub mod oc_moderation_table;
+pub mod oc_of_the_day_table;
+pub mod oc_ranking_table;
 pub mod oc_table;
+pub mod settings_table;
 pub mod short_log_table;
 pub mod startup_log_table;
 pub mod tranfer_datas_table;
@@ -18,6 +24,10 @@ pub struct GachaPlusDatabase {
     pub startup_log_table: startup_log_table::StartupLogTable,
     pub tranfer_datas_table: tranfer_datas_table::TransferDatasTable,
     pub latestversion_table: latestversion_table::LatestVersionTable,
+    pub oc_ranking_table: oc_ranking_table::OcRankingTable,
+    pub oc_of_the_day_table: oc_of_the_day_table::OcOfTheDayTable,
+    pub oc_moderation_table: oc_moderation_table::OcModerationTable,
+    pub settings_table: settings_table::SettingsTable,
 }
 
 impl GachaPlusDatabase {
@@ -50,8 +60,15 @@ impl GachaPlusDatabase {
             oc_random_table: free_oc_table::FreeOcTable::new(shared_pool.clone()),
             short_log_table: short_log_table::ShortLogTable::new(shared_pool.clone()),
             startup_log_table: startup_log_table::StartupLogTable::new(shared_pool.clone()),
-            tranfer_datas_table: tranfer_datas_table::TransferDatasTable::new(shared_pool.clone()),
+            tranfer_datas_table: tranfer_datas_table::TransferDatasTable::new(
+                shared_pool.clone(),
+                TransferCrypto::from_enviorment(),
+            ),
             latestversion_table: latestversion_table::LatestVersionTable::new(shared_pool.clone()),
+            oc_ranking_table: oc_ranking_table::OcRankingTable::new(shared_pool.clone()),
+            oc_of_the_day_table: oc_of_the_day_table::OcOfTheDayTable::new(shared_pool.clone()),
+            oc_moderation_table: oc_moderation_table::OcModerationTable::new(shared_pool.clone()),
+            settings_table: settings_table::SettingsTable::new(shared_pool.clone()),
         }
     }
 }
@@ -1,6 +1,7 @@
 use chrono::serde::ts_seconds;
 use serde::{Deserialize, Serialize};
 use sqlx::{
+    mysql::MySqlQueryResult,
     types::chrono::{DateTime, Utc},
     MySql, MySqlPool, Pool,
 };
@@ -10,25 +11,134 @@ pub struct FreeOcTable {
     pool: Arc<Pool<MySql>>,
 }
 
-#[derive(Debug, sqlx::FromRow, Serialize, Deserialize, Clone)]
+#[derive(Debug, sqlx::FromRow, Serialize, Deserialize, Clone, PartialEq)]
 pub struct FreeOc {
     pub accountx: String,
-    owner: u64,
+    pub owner: u64,
     pub secretid: String,
     pub mycode: String,
+    /// Curator boost for the weighted random selection
+    pub boost: u8,
+    /// Curated order of the pool
+    pub position: u32,
     #[serde(with = "ts_seconds")]
     pub createdate: DateTime<Utc>,
     #[serde(with = "ts_seconds")]
     pub updatedate: DateTime<Utc>,
 }
 
+/// Curator *(owner)* of free OCs.
+#[derive(Debug, sqlx::FromRow, Serialize, Deserialize, Clone)]
+pub struct FreeOcUser {
+    pub id: u64,
+    pub name: String,
+    pub link: String,
+    #[serde(with = "ts_seconds")]
+    pub regdate: DateTime<Utc>,
+}
+
 impl FreeOcTable {
     pub fn new(pool: Arc<Pool<MySql>>) -> Self {
         Self { pool }
     }
     pub async fn get_ocs(&self) -> Result<Vec<FreeOc>, sqlx::Error> {
-        sqlx::query_as!(FreeOc, "SELECT * FROM `freeoc`")
-            .fetch_all(&self.pool as &MySqlPool)
+        sqlx::query_as!(
+            FreeOc,
+            "SELECT * FROM `freeoc` ORDER BY `position`, `accountx`"
+        )
+        .fetch_all(&self.pool as &MySqlPool)
+        .await
+    }
+    /// The rows changed at `since` or later *(the same second is fetched again, because the `timestamp` has no fraction)*.
+    pub async fn get_ocs_updated_since(
+        &self,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<FreeOc>, sqlx::Error> {
+        sqlx::query_as!(
+            FreeOc,
+            "SELECT * FROM `freeoc` WHERE `updatedate` >= ?",
+            since
+        )
+        .fetch_all(&self.pool as &MySqlPool)
+        .await
+    }
+    pub async fn insert_or_update_oc(&self, oc: &FreeOc) -> Result<MySqlQueryResult, sqlx::Error> {
+        sqlx::query!(
+            "INSERT INTO `freeoc`(`accountx`, `owner`, `secretid`, `mycode`, `boost`, `position`) VALUES (?,?,?,?,?,?) ON DUPLICATE KEY UPDATE `owner`=?, `secretid`=?, `mycode`=?, `boost`=?, `position`=?",
+            oc.accountx,
+            oc.owner,
+            oc.secretid,
+            oc.mycode,
+            oc.boost,
+            oc.position,
+            oc.owner,
+            oc.secretid,
+            oc.mycode,
+            oc.boost,
+            oc.position
+        )
+        .execute(&self.pool as &MySqlPool)
+        .await
+    }
+    pub async fn delete_oc(&self, accountx: &str) -> Result<MySqlQueryResult, sqlx::Error> {
+        sqlx::query!("DELETE FROM `freeoc` WHERE `accountx`=?", accountx)
+            .execute(&self.pool as &MySqlPool)
             .await
     }
+    /// Setting the `position`s by the order of the `accountxs` in one transaction.
+    pub async fn reorder(&self, accountxs: &[String]) -> Result<(), sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        for (index, accountx) in accountxs.iter().enumerate() {
+            sqlx::query!(
+                "UPDATE `freeoc` SET `position`=? WHERE `accountx`=?",
+                index as u32 + 1,
+                accountx
+            )
+            .execute(&mut *transaction)
+            .await?;
+        }
+        transaction.commit().await
+    }
+    pub async fn get_users(&self) -> Result<Vec<FreeOcUser>, sqlx::Error> {
+        sqlx::query_as!(
+            FreeOcUser,
+            "SELECT `id`, `name`, `link`, `regdate` FROM `freeoc_user` ORDER BY `id`"
+        )
+        .fetch_all(&self.pool as &MySqlPool)
+        .await
+    }
+    pub async fn get_user(&self, id: u64) -> Result<Option<FreeOcUser>, sqlx::Error> {
+        sqlx::query_as!(
+            FreeOcUser,
+            "SELECT `id`, `name`, `link`, `regdate` FROM `freeoc_user` WHERE `id`=?",
+            id
+        )
+        .fetch_optional(&self.pool as &MySqlPool)
+        .await
+    }
+    pub async fn insert_user(&self, name: &str, link: &str) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            "INSERT INTO `freeoc_user`(`name`, `link`) VALUES (?,?)",
+            name,
+            link
+        )
+        .execute(&self.pool as &MySqlPool)
+        .await?;
+        Ok(result.last_insert_id())
+    }
+    pub async fn update_user(
+        &self,
+        id: u64,
+        name: &str,
+        link: &str,
+    ) -> Result<MySqlQueryResult, sqlx::Error> {
+        sqlx::query!(
+            "UPDATE `freeoc_user` SET `name`=?, `link`=? WHERE `id`=?",
+            name,
+            link,
+            id
+        )
+        .execute(&self.pool as &MySqlPool)
+        .await
+    }
 }
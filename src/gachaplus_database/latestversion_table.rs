@@ -2,15 +2,36 @@ use std::sync::Arc;
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::{MySql, MySqlPool, Pool};
+use sqlx::{mysql::MySqlQueryResult, MySql, MySqlPool, Pool};
 
-#[derive(sqlx::FromRow, Deserialize, Serialize)]
+#[derive(sqlx::FromRow, Deserialize, Serialize, Debug, Clone)]
 pub struct LatestVersionRow {
     pub id: u32,
     pub version: String,
     pub checksum: String,
     pub url: String,
+    /// `Windows`/`Android`, `None` for every platform
+    pub platform: Option<String>,
+    /// `32`/`64`, `None` for every architecture
+    pub xbits: Option<u8>,
+    /// `stable`/`beta`
+    pub channel: String,
+    /// Percent of the clients offered this release, the others get the previous version *(`100` for everyone)*
+    pub rollout: u8,
     pub regdate: DateTime<Utc>,
+    /// When the release was rolled back, it isn't offered since *(`None` for the live releases)*
+    pub rollbackdate: Option<DateTime<Utc>>,
+}
+
+/// A release to publish *(the row without `id` and `regdate`)*.
+pub struct NewRelease<'a> {
+    pub version: &'a str,
+    pub checksum: &'a str,
+    pub url: &'a str,
+    pub platform: Option<&'a str>,
+    pub xbits: Option<u8>,
+    pub channel: &'a str,
+    pub rollout: u8,
 }
 
 pub struct LatestVersionTable {
@@ -20,12 +41,48 @@ impl LatestVersionTable {
     pub fn new(pool: Arc<Pool<MySql>>) -> Self {
         Self { pool }
     }
-    pub async fn get_latest_version(&self) -> Result<LatestVersionRow, sqlx::Error> {
+    /// Every release, the newest first.
+    pub async fn get_versions(&self) -> Result<Vec<LatestVersionRow>, sqlx::Error> {
         sqlx::query_as!(
             LatestVersionRow,
-            "SELECT * FROM `latestversion` ORDER BY `id` DESC LIMIT 1"
+            "SELECT `id`, `version`, `checksum`, `url`, `platform`, `xbits`, `channel`, `rollout`, `regdate`, `rollbackdate` FROM `latestversion` ORDER BY `id` DESC"
+        )
+        .fetch_all(&self.pool as &MySqlPool)
+        .await
+    }
+    /// Publishing a release, returning its `id`.
+    pub async fn insert(&self, release: &NewRelease<'_>) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            "INSERT INTO `latestversion`(`version`, `checksum`, `url`, `platform`, `xbits`, `channel`, `rollout`) VALUES (?,?,?,?,?,?,?)",
+            release.version,
+            release.checksum,
+            release.url,
+            release.platform,
+            release.xbits,
+            release.channel,
+            release.rollout
+        )
+        .execute(&self.pool as &MySqlPool)
+        .await?;
+        Ok(result.last_insert_id())
+    }
+    /// Changing the rollout percent of a release.
+    pub async fn set_rollout(&self, id: u32, rollout: u8) -> Result<MySqlQueryResult, sqlx::Error> {
+        sqlx::query!(
+            "UPDATE `latestversion` SET `rollout`= ? WHERE `id`= ?",
+            rollout,
+            id
+        )
+        .execute(&self.pool as &MySqlPool)
+        .await
+    }
+    /// Marking a release rolled back, so the previous one of its target is the latest again *(the row is kept)*.
+    pub async fn rollback(&self, id: u32) -> Result<MySqlQueryResult, sqlx::Error> {
+        sqlx::query!(
+            "UPDATE `latestversion` SET `rollbackdate`= current_timestamp() WHERE `id`= ? AND `rollbackdate` IS NULL",
+            id
         )
-        .fetch_one(&self.pool as &MySqlPool)
+        .execute(&self.pool as &MySqlPool)
         .await
     }
 }
@@ -0,0 +1,269 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{mysql::MySqlQueryResult, MySql, MySqlPool, Pool, Transaction};
+use std::sync::Arc;
+
+use super::oc_ranking_table::ExcludeReasonEnum;
+
+/// Player reports, moderation decisions and their audit trail.
+pub struct OcModerationTable {
+    pool: Arc<Pool<MySql>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum ModerationStatusEnum {
+    Approved = 1,
+    Hidden = 2,
+}
+impl ModerationStatusEnum {
+    pub fn value(&self) -> u8 {
+        match self {
+            ModerationStatusEnum::Approved => 1,
+            ModerationStatusEnum::Hidden => 2,
+        }
+    }
+    pub fn from_value(num: u8) -> Option<ModerationStatusEnum> {
+        match num {
+            1 => Some(ModerationStatusEnum::Approved),
+            2 => Some(ModerationStatusEnum::Hidden),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum ModerationActionEnum {
+    Approve = 1,
+    Hide = 2,
+    Delete = 3,
+    AutoHide = 4,
+}
+impl ModerationActionEnum {
+    pub fn value(&self) -> u8 {
+        match self {
+            ModerationActionEnum::Approve => 1,
+            ModerationActionEnum::Hide => 2,
+            ModerationActionEnum::Delete => 3,
+            ModerationActionEnum::AutoHide => 4,
+        }
+    }
+    pub fn from_value(num: u8) -> Option<ModerationActionEnum> {
+        match num {
+            1 => Some(ModerationActionEnum::Approve),
+            2 => Some(ModerationActionEnum::Hide),
+            3 => Some(ModerationActionEnum::Delete),
+            4 => Some(ModerationActionEnum::AutoHide),
+            _ => None,
+        }
+    }
+}
+
+/// Reported OC in the moderation queue.
+#[derive(Debug, sqlx::FromRow, Serialize, Deserialize, Clone)]
+pub struct ReportedOc {
+    pub accountx: String,
+    pub reports: u64,
+    pub reasons: Option<String>,
+    pub last_report: Option<DateTime<Utc>>,
+    pub status: Option<u8>,
+}
+
+#[derive(Debug, sqlx::FromRow, Serialize, Deserialize, Clone)]
+pub struct ModerationLog {
+    pub id: u32,
+    pub accountx: String,
+    pub action: u8,
+    /// Hashed `IP` of the moderator *(`0` for the automatic decisions)*
+    pub moderator: u32,
+    pub reports: u32,
+    pub regdate: DateTime<Utc>,
+}
+
+impl OcModerationTable {
+    pub fn new(pool: Arc<Pool<MySql>>) -> Self {
+        Self { pool }
+    }
+    /// Adding a report, one per reporter and OC.
+    pub async fn insert_report(
+        &self,
+        accountx: &str,
+        reason: &str,
+        reporter: u32,
+    ) -> Result<MySqlQueryResult, sqlx::Error> {
+        sqlx::query!(
+            "INSERT IGNORE INTO `oc_report`(`accountx`, `reason`, `reporter`) VALUES (?,?,?)",
+            accountx,
+            reason,
+            reporter
+        )
+        .execute(&self.pool as &MySqlPool)
+        .await
+    }
+    pub async fn count_reports(&self, accountx: &str) -> Result<u64, sqlx::Error> {
+        let row = sqlx::query!(
+            "SELECT CAST(COUNT(*) AS UNSIGNED) AS `reports` FROM `oc_report` WHERE `accountx`=?",
+            accountx
+        )
+        .fetch_one(&self.pool as &MySqlPool)
+        .await?;
+        Ok(row.reports)
+    }
+    pub async fn get_reported(&self) -> Result<Vec<ReportedOc>, sqlx::Error> {
+        sqlx::query_as!(
+            ReportedOc,
+            "SELECT r.`accountx`, CAST(COUNT(*) AS UNSIGNED) AS `reports`, GROUP_CONCAT(r.`reason` ORDER BY r.`id` DESC SEPARATOR '\n') AS `reasons`, MAX(r.`regdate`) AS `last_report`, m.`status` FROM `oc_report` r LEFT JOIN `oc_moderation` m ON m.`accountx` = r.`accountx` GROUP BY r.`accountx`, m.`status` ORDER BY `reports` DESC LIMIT 100"
+        )
+        .fetch_all(&self.pool as &MySqlPool)
+        .await
+    }
+    pub async fn get_status(
+        &self,
+        accountx: &str,
+    ) -> Result<Option<ModerationStatusEnum>, sqlx::Error> {
+        let row = sqlx::query!(
+            "SELECT `status` FROM `oc_moderation` WHERE `accountx`=?",
+            accountx
+        )
+        .fetch_optional(&self.pool as &MySqlPool)
+        .await?;
+        Ok(row.and_then(|row| ModerationStatusEnum::from_value(row.status)))
+    }
+    pub async fn get_hidden(&self) -> Result<Vec<String>, sqlx::Error> {
+        let rows = sqlx::query!(
+            "SELECT `accountx` FROM `oc_moderation` WHERE `status`=?",
+            ModerationStatusEnum::Hidden.value()
+        )
+        .fetch_all(&self.pool as &MySqlPool)
+        .await?;
+        Ok(rows.into_iter().map(|row| row.accountx).collect())
+    }
+    /// Approving the OC, so the later reports don't hide it automatically *(in one transaction)*.
+    pub async fn approve(&self, accountx: &str, moderator: u32) -> Result<(), sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let reports = Self::count_reports_in(&mut transaction, accountx).await?;
+        Self::set_status_in(&mut transaction, accountx, ModerationStatusEnum::Approved).await?;
+        sqlx::query!("DELETE FROM `oc_report` WHERE `accountx`=?", accountx)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query!(
+            "DELETE FROM `oc_ranking_exclude` WHERE `accountx`=? AND `reason`=?",
+            accountx,
+            ExcludeReasonEnum::Moderated.value()
+        )
+        .execute(&mut *transaction)
+        .await?;
+        Self::insert_log_in(
+            &mut transaction,
+            accountx,
+            ModerationActionEnum::Approve,
+            moderator,
+            reports,
+        )
+        .await?;
+        transaction.commit().await
+    }
+    /// Hiding the OC from the random pool and the ranking *(in one transaction)*.
+    pub async fn hide(
+        &self,
+        accountx: &str,
+        action: ModerationActionEnum,
+        moderator: u32,
+    ) -> Result<(), sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let reports = Self::count_reports_in(&mut transaction, accountx).await?;
+        Self::set_status_in(&mut transaction, accountx, ModerationStatusEnum::Hidden).await?;
+        Self::exclude_in(&mut transaction, accountx).await?;
+        Self::insert_log_in(&mut transaction, accountx, action, moderator, reports).await?;
+        transaction.commit().await
+    }
+    /// Deleting the OC *(also from the free OCs)* with its reports and status, keeping the audit log *(in one transaction)*.
+    pub async fn delete(&self, accountx: &str, moderator: u32) -> Result<(), sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let reports = Self::count_reports_in(&mut transaction, accountx).await?;
+        sqlx::query!("DELETE FROM `oc` WHERE `accountx`=?", accountx)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query!("DELETE FROM `freeoc` WHERE `accountx`=?", accountx)
+            .execute(&mut *transaction)
+            .await?;
+        Self::exclude_in(&mut transaction, accountx).await?;
+        sqlx::query!("DELETE FROM `oc_report` WHERE `accountx`=?", accountx)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query!("DELETE FROM `oc_moderation` WHERE `accountx`=?", accountx)
+            .execute(&mut *transaction)
+            .await?;
+        Self::insert_log_in(
+            &mut transaction,
+            accountx,
+            ModerationActionEnum::Delete,
+            moderator,
+            reports,
+        )
+        .await?;
+        transaction.commit().await
+    }
+    async fn count_reports_in(
+        transaction: &mut Transaction<'_, MySql>,
+        accountx: &str,
+    ) -> Result<u32, sqlx::Error> {
+        let row = sqlx::query!(
+            "SELECT CAST(COUNT(*) AS UNSIGNED) AS `reports` FROM `oc_report` WHERE `accountx`=?",
+            accountx
+        )
+        .fetch_one(&mut **transaction)
+        .await?;
+        Ok(row.reports as u32)
+    }
+    async fn set_status_in(
+        transaction: &mut Transaction<'_, MySql>,
+        accountx: &str,
+        status: ModerationStatusEnum,
+    ) -> Result<MySqlQueryResult, sqlx::Error> {
+        sqlx::query!(
+            "INSERT INTO `oc_moderation`(`accountx`, `status`) VALUES (?,?) ON DUPLICATE KEY UPDATE `status`=?",
+            accountx,
+            status.value(),
+            status.value()
+        )
+        .execute(&mut **transaction)
+        .await
+    }
+    async fn exclude_in(
+        transaction: &mut Transaction<'_, MySql>,
+        accountx: &str,
+    ) -> Result<MySqlQueryResult, sqlx::Error> {
+        sqlx::query!(
+            "INSERT IGNORE INTO `oc_ranking_exclude`(`accountx`, `reason`) VALUES (?,?)",
+            accountx,
+            ExcludeReasonEnum::Moderated.value()
+        )
+        .execute(&mut **transaction)
+        .await
+    }
+    async fn insert_log_in(
+        transaction: &mut Transaction<'_, MySql>,
+        accountx: &str,
+        action: ModerationActionEnum,
+        moderator: u32,
+        reports: u32,
+    ) -> Result<MySqlQueryResult, sqlx::Error> {
+        sqlx::query!(
+            "INSERT INTO `oc_moderation_log`(`accountx`, `action`, `moderator`, `reports`) VALUES (?,?,?,?)",
+            accountx,
+            action.value(),
+            moderator,
+            reports
+        )
+        .execute(&mut **transaction)
+        .await
+    }
+    pub async fn get_logs(&self) -> Result<Vec<ModerationLog>, sqlx::Error> {
+        sqlx::query_as!(
+            ModerationLog,
+            "SELECT * FROM `oc_moderation_log` ORDER BY `id` DESC LIMIT 100"
+        )
+        .fetch_all(&self.pool as &MySqlPool)
+        .await
+    }
+}
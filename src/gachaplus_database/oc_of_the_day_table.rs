@@ -0,0 +1,82 @@
+use chrono::NaiveDate;
+use sqlx::{mysql::MySqlQueryResult, MySql, MySqlPool, Pool};
+use std::sync::Arc;
+
+/// OCs of the days: the curator pins and the stored picks.
+pub struct OcOfTheDayTable {
+    pool: Arc<Pool<MySql>>,
+}
+
+#[derive(Debug)]
+pub struct DailyOcRow {
+    pub accountx: String,
+    /// Cycle of the pick, `None` for a curator pin
+    pub cycle: Option<u32>,
+}
+
+impl OcOfTheDayTable {
+    pub fn new(pool: Arc<Pool<MySql>>) -> Self {
+        Self { pool }
+    }
+    pub async fn get_day(&self, day: NaiveDate) -> Result<Option<DailyOcRow>, sqlx::Error> {
+        sqlx::query_as!(
+            DailyOcRow,
+            "SELECT `accountx`, `cycle` FROM `oc_of_the_day` WHERE `day`=?",
+            day
+        )
+        .fetch_optional(&self.pool as &MySqlPool)
+        .await
+    }
+    pub async fn set_pin(
+        &self,
+        day: NaiveDate,
+        accountx: &str,
+    ) -> Result<MySqlQueryResult, sqlx::Error> {
+        sqlx::query!(
+            "INSERT INTO `oc_of_the_day`(`day`, `accountx`) VALUES (?,?) ON DUPLICATE KEY UPDATE `accountx`=?, `cycle`=NULL",
+            day,
+            accountx,
+            accountx
+        )
+        .execute(&self.pool as &MySqlPool)
+        .await
+    }
+    pub async fn delete_pin(&self, day: NaiveDate) -> Result<MySqlQueryResult, sqlx::Error> {
+        sqlx::query!("DELETE FROM `oc_of_the_day` WHERE `day`=?", day)
+            .execute(&self.pool as &MySqlPool)
+            .await
+    }
+    /// Storing the pick of the `day`, unless the day already has one *(`rows_affected` is `0` then)*.
+    pub async fn insert_pick(
+        &self,
+        day: NaiveDate,
+        accountx: &str,
+        cycle: u32,
+    ) -> Result<MySqlQueryResult, sqlx::Error> {
+        sqlx::query!(
+            "INSERT IGNORE INTO `oc_of_the_day`(`day`, `accountx`, `cycle`) VALUES (?,?,?)",
+            day,
+            accountx,
+            cycle
+        )
+        .execute(&self.pool as &MySqlPool)
+        .await
+    }
+    /// The current cycle of the picks, `0` before the first one.
+    pub async fn get_last_cycle(&self) -> Result<u32, sqlx::Error> {
+        let row = sqlx::query!("SELECT MAX(`cycle`) AS `cycle` FROM `oc_of_the_day`")
+            .fetch_one(&self.pool as &MySqlPool)
+            .await?;
+        Ok(row.cycle.unwrap_or_default())
+    }
+    /// The OCs featured in the `cycle`.
+    pub async fn get_cycle(&self, cycle: u32) -> Result<Vec<String>, sqlx::Error> {
+        let rows = sqlx::query!(
+            "SELECT `accountx` FROM `oc_of_the_day` WHERE `cycle`=?",
+            cycle
+        )
+        .fetch_all(&self.pool as &MySqlPool)
+        .await?;
+        Ok(rows.into_iter().map(|row| row.accountx).collect())
+    }
+}
@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{mysql::MySqlQueryResult, MySql, MySqlPool, Pool};
+use std::sync::Arc;
+
+pub struct OcRankingTable {
+    pool: Arc<Pool<MySql>>,
+}
+
+/// Imports of an OC in one hour *(hours since the unix epoch)*.
+#[derive(Debug, sqlx::FromRow, Serialize, Deserialize, Clone)]
+pub struct OcRankingRow {
+    pub accountx: String,
+    pub hour: u32,
+    pub imports: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum ExcludeReasonEnum {
+    OptOut = 1,
+    Moderated = 2,
+}
+impl ExcludeReasonEnum {
+    pub fn value(&self) -> u8 {
+        match self {
+            ExcludeReasonEnum::OptOut => 1,
+            ExcludeReasonEnum::Moderated => 2,
+        }
+    }
+}
+
+impl OcRankingTable {
+    pub fn new(pool: Arc<Pool<MySql>>) -> Self {
+        Self { pool }
+    }
+    /// The all-time top OCs *(the compacted totals and the kept hourly rows)*, without the excluded ones.
+    pub async fn get_all_time_top(&self, limit: u32) -> Result<Vec<(String, u64)>, sqlx::Error> {
+        let rows = sqlx::query!(
+            "SELECT `all_time`.`accountx`, CAST(SUM(`all_time`.`imports`) AS UNSIGNED) AS `imports` FROM (SELECT `accountx`, `imports` FROM `oc_ranking_total` UNION ALL SELECT `accountx`, `imports` FROM `oc_ranking`) AS `all_time` WHERE `all_time`.`accountx` NOT IN (SELECT `accountx` FROM `oc_ranking_exclude`) GROUP BY `all_time`.`accountx` ORDER BY `imports` DESC, `all_time`.`accountx` LIMIT ?",
+            limit
+        )
+        .fetch_all(&self.pool as &MySqlPool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.accountx, row.imports))
+            .collect())
+    }
+    /// Adding the hourly rows older than `before_hour` to the all-time totals and deleting them, in one transaction.
+    ///
+    /// Returns the number of deleted hourly rows.
+    pub async fn compact(&self, before_hour: u32) -> Result<u64, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        sqlx::query!(
+            "INSERT INTO `oc_ranking_total`(`accountx`, `imports`) SELECT * FROM (SELECT `accountx` AS `compacted_accountx`, SUM(`imports`) AS `compacted_imports` FROM `oc_ranking` WHERE `hour` < ? GROUP BY `accountx`) AS `compacted` ON DUPLICATE KEY UPDATE `imports`=`imports`+`compacted_imports`",
+            before_hour
+        )
+        .execute(&mut *transaction)
+        .await?;
+        let deleted = sqlx::query!("DELETE FROM `oc_ranking` WHERE `hour` < ?", before_hour)
+            .execute(&mut *transaction)
+            .await?
+            .rows_affected();
+        transaction.commit().await?;
+        Ok(deleted)
+    }
+    pub async fn get_since(&self, hour: u32) -> Result<Vec<OcRankingRow>, sqlx::Error> {
+        sqlx::query_as!(
+            OcRankingRow,
+            "SELECT `accountx`, `hour`, `imports` FROM `oc_ranking` WHERE `hour` >= ?",
+            hour
+        )
+        .fetch_all(&self.pool as &MySqlPool)
+        .await
+    }
+    pub async fn insert_or_update(
+        &self,
+        row: &OcRankingRow,
+    ) -> Result<MySqlQueryResult, sqlx::Error> {
+        sqlx::query!(
+            "INSERT INTO `oc_ranking`(`accountx`, `hour`, `imports`) VALUES (?,?,?) ON DUPLICATE KEY UPDATE `imports`=?",
+            row.accountx,
+            row.hour,
+            row.imports,
+            row.imports
+        )
+        .execute(&self.pool as &MySqlPool)
+        .await
+    }
+    pub async fn get_excluded(&self) -> Result<Vec<String>, sqlx::Error> {
+        let rows = sqlx::query!("SELECT DISTINCT `accountx` FROM `oc_ranking_exclude`")
+            .fetch_all(&self.pool as &MySqlPool)
+            .await?;
+        Ok(rows.into_iter().map(|row| row.accountx).collect())
+    }
+    pub async fn exclude(
+        &self,
+        accountx: &str,
+        reason: ExcludeReasonEnum,
+    ) -> Result<MySqlQueryResult, sqlx::Error> {
+        sqlx::query!(
+            "INSERT IGNORE INTO `oc_ranking_exclude`(`accountx`, `reason`) VALUES (?,?)",
+            accountx,
+            reason.value()
+        )
+        .execute(&self.pool as &MySqlPool)
+        .await
+    }
+    pub async fn include(
+        &self,
+        accountx: &str,
+        reason: ExcludeReasonEnum,
+    ) -> Result<MySqlQueryResult, sqlx::Error> {
+        sqlx::query!(
+            "DELETE FROM `oc_ranking_exclude` WHERE `accountx`=? AND `reason`=?",
+            accountx,
+            reason.value()
+        )
+        .execute(&self.pool as &MySqlPool)
+        .await
+    }
+}
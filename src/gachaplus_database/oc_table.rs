@@ -1,12 +1,16 @@
 use serde::{Deserialize, Serialize};
 use sqlx::{
+    mysql::MySqlQueryResult,
     types::chrono::{DateTime, Utc},
     MySql, MySqlPool, Pool,
 };
 use std::{error::Error, sync::Arc};
 
+use crate::oc_cache::OcCache;
+
 pub struct OcTable {
     pool: Arc<Pool<MySql>>,
+    pub cache: OcCache,
 }
 
 #[derive(Debug, sqlx::FromRow, Serialize, Deserialize, Clone)]
@@ -33,17 +37,24 @@ impl Oc {
 
 impl OcTable {
     pub fn new(pool: Arc<Pool<MySql>>) -> Self {
-        Self { pool }
+        Self {
+            pool,
+            cache: OcCache::default(),
+        }
     }
     pub async fn get_oc(&self, accountx: &str) -> Result<Oc, sqlx::Error> {
-        sqlx::query_as!(Oc, "SELECT * FROM `oc` WHERE `accountx` = ?", accountx)
+        if let Some(oc) = self.cache.get(accountx).await {
+            return Ok(oc);
+        }
+        // read before the query, so an update during it drops this fill
+        let generation = self.cache.generation().await;
+        let oc = sqlx::query_as!(Oc, "SELECT * FROM `oc` WHERE `accountx` = ?", accountx)
             .fetch_one(&self.pool as &MySqlPool)
-            .await
+            .await?;
+        self.cache.insert(oc.clone(), generation).await;
+        Ok(oc)
     }
-    pub async fn insert_or_update_oc(
-        &self,
-        oc: Oc,
-    ) -> Result<sqlx::mysql::MySqlQueryResult, Box<dyn Error>> {
+    pub async fn insert_or_update_oc(&self, oc: Oc) -> Result<MySqlQueryResult, Box<dyn Error>> {
         let row_option = sqlx::query!(
             "SELECT `secretid` FROM `oc` WHERE `accountx`=?",
             oc.accountx
@@ -75,6 +86,7 @@ impl OcTable {
                 .await?
             }
         };
+        self.cache.invalidate(&oc.accountx).await;
         Ok(res)
     }
 }
@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{mysql::MySqlQueryResult, MySql, MySqlPool, Pool};
+use std::{collections::HashMap, sync::Arc};
+
+pub struct SettingsTable {
+    pool: Arc<Pool<MySql>>,
+}
+
+#[derive(Debug, sqlx::FromRow, Serialize, Deserialize, Clone)]
+pub struct SettingRow {
+    pub name: String,
+    pub value: String,
+}
+
+impl SettingsTable {
+    pub fn new(pool: Arc<Pool<MySql>>) -> Self {
+        Self { pool }
+    }
+    pub async fn get_all(&self) -> Result<HashMap<String, String>, sqlx::Error> {
+        let rows = sqlx::query_as!(SettingRow, "SELECT `name`, `value` FROM `settings`")
+            .fetch_all(&self.pool as &MySqlPool)
+            .await?;
+        Ok(rows.into_iter().map(|row| (row.name, row.value)).collect())
+    }
+    pub async fn set(&self, name: &str, value: &str) -> Result<MySqlQueryResult, sqlx::Error> {
+        sqlx::query!(
+            "INSERT INTO `settings`(`name`, `value`) VALUES (?,?) ON DUPLICATE KEY UPDATE `value`=?",
+            name,
+            value,
+            value
+        )
+        .execute(&self.pool as &MySqlPool)
+        .await
+    }
+    pub async fn delete(&self, name: &str) -> Result<MySqlQueryResult, sqlx::Error> {
+        sqlx::query!("DELETE FROM `settings` WHERE `name`=?", name)
+            .execute(&self.pool as &MySqlPool)
+            .await
+    }
+}
@@ -24,6 +24,7 @@ pub enum ActionEnum {
     ALLexport = 5,
     ALLimport = 6,
     Startups = 7,
+    OCdaily = 8,
 }
 impl ActionEnum {
     pub fn value(&self) -> u8 {
@@ -35,6 +36,7 @@ impl ActionEnum {
             ActionEnum::ALLexport => 5,
             ActionEnum::ALLimport => 6,
             ActionEnum::Startups => 7,
+            ActionEnum::OCdaily => 8,
         }
     }
 
@@ -48,6 +50,7 @@ impl ActionEnum {
             5 => Some(ActionEnum::ALLexport),
             6 => Some(ActionEnum::ALLimport),
             7 => Some(ActionEnum::Startups),
+            8 => Some(ActionEnum::OCdaily),
             _ => None,
         }
     }
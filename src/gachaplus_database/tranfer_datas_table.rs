@@ -1,118 +1,738 @@
-use std::{error::Error, sync::Arc};
+use std::{collections::BTreeMap, error::Error, fmt, sync::Arc};
 
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
-use sqlx::{mysql::MySqlQueryResult, prelude::FromRow, MySql, MySqlPool, Pool};
+use serde::{
+    de::{self, IgnoredAny, MapAccess, Visitor},
+    ser::SerializeMap,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use sqlx::{mysql::MySqlQueryResult, prelude::FromRow, MySql, MySqlPool, Pool, Transaction};
 
-use crate::character_code::CharacterCode;
+use crate::{
+    save_data::{SlotKind, SLOTS},
+    transfer_crypto::TransferCrypto,
+    transfer_owner,
+};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// Transfer of a player's save: the `accountx` and the datastrings by slot.
+///
+/// *(Serialized as `accountx` and `datastring<slot>` pairs, like the form of the client and the stored JSON.)*
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct TransferDatas {
     pub accountx: String,
-    pub datastring1: String,
-    pub datastring2: String,
-    pub datastring3: String,
-    pub datastring4: String,
-    pub datastring5: String,
-    pub datastring6: String,
-    pub datastring7: String,
-    pub datastring8: String,
-    pub datastring9: String,
-    pub datastring10: String,
-    pub datastring11: String,
-    pub datastring12: String,
-    pub datastring13: String,
-    pub datastring14: String,
-    pub datastring15: String,
-    pub datastring16: String,
-    pub datastring17: String,
-    pub datastring18: String,
-    pub datastring19: String,
-    pub datastring20: String,
+    /// `slot` => datastring
+    pub datastrings: BTreeMap<u8, String>,
 }
 impl TransferDatas {
+    /// The datastring of the `slot` *(empty if it's missing)*.
+    pub fn datastring(&self, slot: u8) -> &str {
+        self.datastrings
+            .get(&slot)
+            .map_or("", |datastring| datastring)
+    }
+
+    /// Replacing the datastrings given in the `update` *(an empty one clears its slot)*, the others stay.
+    pub fn merge(&mut self, update: TransferDatas) {
+        self.datastrings.extend(update.datastrings);
+    }
+
+    /// Checking the `accountx` and every character.
     pub fn is_invalid(&self) -> Option<String> {
+        self.is_invalid_by(|_| true)
+    }
+
+    /// Checking the `accountx` and only the given characters *(for the partial updates)*.
+    pub fn is_invalid_given(&self) -> Option<String> {
+        self.is_invalid_by(|slot| self.datastrings.contains_key(&slot))
+    }
+
+    fn is_invalid_by(&self, filter: impl Fn(u8) -> bool) -> Option<String> {
         let accountx = self.accountx.parse::<u32>().unwrap_or_default();
-        if accountx < 100_000_000 || accountx > 999_999_999 {
-            Some(format!("Transferdata invalid: `accountx`"))
-        } else if let Err(error) = CharacterCode::new_from_code(&self.datastring9) {
-            Some(format!("Transferdata invalid: `datastring9` ({error})"))
-        } else if let Err(error) = CharacterCode::new_from_code(&self.datastring10) {
-            Some(format!("Transferdata invalid: `datastring10` ({error})"))
-        } else if let Err(error) = CharacterCode::new_from_code(&self.datastring11) {
-            Some(format!("Transferdata invalid: `datastring11` ({error})"))
-        } else if let Err(error) = CharacterCode::new_from_code(&self.datastring12) {
-            Some(format!("Transferdata invalid: `datastring12` ({error})"))
-        } else if let Err(error) = CharacterCode::new_from_code(&self.datastring13) {
-            Some(format!("Transferdata invalid: `datastring13` ({error})"))
-        } else if let Err(error) = CharacterCode::new_from_code(&self.datastring14) {
-            Some(format!("Transferdata invalid: `datastring14` ({error})"))
-        } else if let Err(error) = CharacterCode::new_from_code(&self.datastring15) {
-            Some(format!("Transferdata invalid: `datastring15` ({error})"))
-        } else if let Err(error) = CharacterCode::new_from_code(&self.datastring16) {
-            Some(format!("Transferdata invalid: `datastring16` ({error})"))
-        } else if let Err(error) = CharacterCode::new_from_code(&self.datastring17) {
-            Some(format!("Transferdata invalid: `datastring17` ({error})"))
-        } else if let Err(error) = CharacterCode::new_from_code(&self.datastring18) {
-            Some(format!("Transferdata invalid: `datastring18` ({error})"))
-        } else {
-            None
+        if !(100_000_000..=999_999_999).contains(&accountx) {
+            return Some("Transferdata invalid: `accountx`".to_owned());
         }
+        SLOTS
+            .iter()
+            .filter(|(slot, _)| filter(*slot))
+            .find_map(|(slot, kind)| {
+                let error = kind.validate(self.datastring(*slot)).err()?;
+                Some(format!(
+                    "Transferdata invalid: `datastring{slot}` ({error})"
+                ))
+            })
     }
 }
 
-impl From<Vec<u8>> for TransferDatas {
-    fn from(value: Vec<u8>) -> Self {
-        serde_json::from_slice(&value).unwrap()
+impl Serialize for TransferDatas {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.datastrings.len() + 1))?;
+        map.serialize_entry("accountx", &self.accountx)?;
+        for (slot, datastring) in self.datastrings.iter() {
+            map.serialize_entry(&format!("datastring{slot}"), datastring)?;
+        }
+        map.end()
     }
 }
 
-#[derive(FromRow, Debug)]
+impl<'de> Deserialize<'de> for TransferDatas {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(TransferDatasVisitor)
+    }
+}
+
+struct TransferDatasVisitor;
+impl<'de> Visitor<'de> for TransferDatasVisitor {
+    type Value = TransferDatas;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("`accountx` and `datastring<slot>` pairs")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut accountx = None;
+        let mut datastrings = BTreeMap::new();
+        while let Some(key) = map.next_key::<String>()? {
+            let slot = key
+                .strip_prefix("datastring")
+                .and_then(|slot| slot.parse::<u8>().ok())
+                .filter(|slot| SlotKind::from_slot(*slot).is_some());
+            if key == "accountx" {
+                accountx = Some(map.next_value()?);
+            } else if let Some(slot) = slot {
+                datastrings.insert(slot, map.next_value()?);
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        Ok(TransferDatas {
+            accountx: accountx.ok_or_else(|| de::Error::missing_field("accountx"))?,
+            datastrings,
+        })
+    }
+}
+
+impl TransferDatas {
+    /// The JSON stored in `transfer.data`.
+    pub fn to_stored(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+
+    /// Decoding the stored JSON *(an error instead of a panic for a corrupt row)*.
+    pub fn from_stored(data: &[u8]) -> Result<Self, String> {
+        serde_json::from_slice(data).map_err(|error| format!("Invalid JSON: {error}"))
+    }
+}
+
+#[derive(Debug)]
 pub struct TransferDatasRow {
     pub accountx: u32,
     pub data: TransferDatas,
     pub used: u16,
     pub regdate: DateTime<Utc>,
+    /// Hashed PIN *(`None` for the transfers without PIN)*
+    pub pin: Option<String>,
+}
+
+/// What protects a transfer from the others: the hashed PIN and the hashed owner token.
+#[derive(FromRow, Debug)]
+pub struct TransferProtection {
+    pub pin: Option<String>,
+    pub owner_token: Option<String>,
+}
+
+/// Result of a write checked against the owner and the PIN in its transaction.
+#[derive(Debug, PartialEq)]
+pub enum TransferWrite {
+    Written,
+    /// The token isn't the owner's
+    NotOwner,
+    /// The PIN changed since it was checked
+    PinChanged,
+    /// No transfer to update
+    NotFound,
+}
+
+/// Checking the `token` against the owner and the PIN against the one the caller verified *(`pin_checked`)*.
+fn check_access(
+    reservation: Option<&str>,
+    protection: Option<&TransferProtection>,
+    token: Option<&str>,
+    pin_checked: Option<&str>,
+) -> Result<(), TransferWrite> {
+    let owner = protection.and_then(|protection| protection.owner_token.as_deref());
+    if !transfer_owner::is_owner(reservation, owner, token) {
+        return Err(TransferWrite::NotOwner);
+    }
+    if protection.and_then(|protection| protection.pin.as_deref()) != pin_checked {
+        return Err(TransferWrite::PinChanged);
+    }
+    Ok(())
+}
+
+/// Stored transfer data waiting for the re-encryption *(`id` is the snapshot's)*.
+#[derive(FromRow, Debug)]
+pub struct StoredTransferData {
+    pub id: Option<u64>,
+    pub accountx: u32,
+    pub data: Vec<u8>,
+}
+
+/// Earlier `data` of a transfer, saved when an upload replaced it *(still encrypted)*.
+#[derive(FromRow, Debug)]
+pub struct TransferSnapshotRow {
+    pub id: u64,
+    pub accountx: u32,
+    pub data: Vec<u8>,
+    pub key_id: Option<String>,
+    pub regdate: DateTime<Utc>,
 }
 
 pub struct TransferDatasTable {
     pool: Arc<Pool<MySql>>,
+    pub crypto: TransferCrypto,
 }
 
 impl TransferDatasTable {
-    pub fn new(pool: Arc<Pool<MySql>>) -> Self {
-        Self { pool }
+    pub fn new(pool: Arc<Pool<MySql>>, crypto: TransferCrypto) -> Self {
+        Self { pool, crypto }
     }
     pub async fn get(&self, accountx: u32) -> Result<TransferDatasRow, sqlx::Error> {
-        sqlx::query_as!(
-            TransferDatasRow,
-            "SELECT * FROM `transfer` WHERE `accountx`= ?",
+        let row = sqlx::query!(
+            "SELECT `accountx`, `data`, `used`, `regdate`, `pin` FROM `transfer` WHERE `accountx`= ?",
             accountx
         )
         .fetch_one(&self.pool as &MySqlPool)
-        .await
+        .await?;
+        let data = self
+            .crypto
+            .decrypt(&row.accountx.to_string(), &row.data)
+            .and_then(|data| TransferDatas::from_stored(&data))
+            .map_err(|error| sqlx::Error::Decode(error.into()))?;
+        Ok(TransferDatasRow {
+            accountx: row.accountx,
+            data,
+            used: row.used,
+            regdate: row.regdate,
+            pin: row.pin,
+        })
     }
-    pub async fn update(&self, accountx: u32) -> Result<MySqlQueryResult, sqlx::Error> {
-        sqlx::query!(
-            "UPDATE `transfer` SET `used`=`used`+1 WHERE `accountx`= ?",
-            accountx
+    /// Counting a load, unless the row already has `max_uses` loads *(`0` is unlimited)*.
+    ///
+    /// Returning `false` if the row is used up.
+    pub async fn update(&self, accountx: u32, max_uses: u16) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            "UPDATE `transfer` SET `used`=`used`+1 WHERE `accountx`= ? AND (? = 0 OR `used` < ?)",
+            accountx,
+            max_uses,
+            max_uses
         )
         .execute(&self.pool as &MySqlPool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+    pub async fn delete_used(&self, max_uses: u16) -> Result<MySqlQueryResult, sqlx::Error> {
+        sqlx::query!("DELETE FROM `transfer` WHERE `used` >= ?", max_uses)
+            .execute(&self.pool as &MySqlPool)
+            .await
+    }
+    pub async fn delete_older(
+        &self,
+        regdate: DateTime<Utc>,
+    ) -> Result<MySqlQueryResult, sqlx::Error> {
+        sqlx::query!("DELETE FROM `transfer` WHERE `regdate` < ?", regdate)
+            .execute(&self.pool as &MySqlPool)
+            .await
+    }
+    #[cfg(test)]
+    pub async fn delete(&self, accountx: u32) -> Result<MySqlQueryResult, sqlx::Error> {
+        sqlx::query!("DELETE FROM `transfer` WHERE `accountx`= ?", accountx)
+            .execute(&self.pool as &MySqlPool)
+            .await
+    }
+    /// Hashed PIN and owner token of the transfer, `None` if there is no transfer.
+    pub async fn get_protection(
+        &self,
+        accountx: u32,
+    ) -> Result<Option<TransferProtection>, sqlx::Error> {
+        sqlx::query_as!(
+            TransferProtection,
+            "SELECT `pin`, `owner_token` FROM `transfer` WHERE `accountx`= ?",
+            accountx
+        )
+        .fetch_optional(&self.pool as &MySqlPool)
         .await
     }
+    /// Uploading the transfer, a new upload starts the expiry again.
+    ///
+    /// The owner and the PIN are checked again with the rows locked, `pin_checked` is the hashed PIN the caller
+    /// verified. A new transfer gets the owner of its reservation, an existing one keeps its owner *(so a token
+    /// can't claim a transfer without owner)*. The replaced `data` is kept as a snapshot, with the last
+    /// `snapshots` ones per transfer.
     pub async fn insert_or_update(
         &self,
         datas: TransferDatas,
-    ) -> Result<MySqlQueryResult, Box<dyn Error>> {
-        let data_json = serde_json::to_string(&datas)?;
-        let result = sqlx::query!(
-            "INSERT INTO `transfer`(`accountx`, `data`) VALUES (?,?) ON DUPLICATE KEY UPDATE `data`=?", 
+        pin: Option<String>,
+        token: Option<&str>,
+        pin_checked: Option<&str>,
+        snapshots: u32,
+    ) -> Result<TransferWrite, Box<dyn Error + Send + Sync>> {
+        let data_json = datas.to_stored()?;
+        let (data, key_id) = self.crypto.encrypt(&datas.accountx, &data_json)?;
+        let accountx = datas.accountx.parse::<u32>()?;
+        let mut transaction = self.pool.begin().await?;
+        let (reservation, protection) = Self::lock_access(&mut transaction, accountx).await?;
+        if let Err(denied) = check_access(
+            reservation.as_deref(),
+            protection.as_ref(),
+            token,
+            pin_checked,
+        ) {
+            return Ok(denied);
+        }
+        Self::snapshot(&mut transaction, accountx, snapshots).await?;
+        sqlx::query!(
+            "INSERT INTO `transfer`(`accountx`, `data`, `key_id`, `pin`, `owner_token`) VALUES (?,?,?,?,?) ON DUPLICATE KEY UPDATE `data`=?, `key_id`=?, `pin`=?, `used`=0, `regdate`=CURRENT_TIMESTAMP()",
             datas.accountx,
-            &data_json,
-            &data_json
+            &data,
+            key_id,
+            pin,
+            reservation,
+            &data,
+            key_id,
+            pin
         )
-            .execute(&self.pool as &MySqlPool)
+            .execute(&mut *transaction)
+            .await?;
+        if reservation.is_some() {
+            // the transfer keeps the owner
+            sqlx::query!(
+                "DELETE FROM `transfer_reservation` WHERE `accountx`= ?",
+                accountx
+            )
+            .execute(&mut *transaction)
             .await?;
-        Ok(result)
+        }
+        transaction.commit().await?;
+        Ok(TransferWrite::Written)
+    }
+    /// The reservation and the protection of the transfer, locked until the end of the `transaction`.
+    async fn lock_access(
+        transaction: &mut Transaction<'_, MySql>,
+        accountx: u32,
+    ) -> Result<(Option<String>, Option<TransferProtection>), sqlx::Error> {
+        let reservation = sqlx::query!(
+            "SELECT `token` FROM `transfer_reservation` WHERE `accountx`= ? FOR UPDATE",
+            accountx
+        )
+        .fetch_optional(&mut **transaction)
+        .await?;
+        let protection = sqlx::query_as!(
+            TransferProtection,
+            "SELECT `pin`, `owner_token` FROM `transfer` WHERE `accountx`= ? FOR UPDATE",
+            accountx
+        )
+        .fetch_optional(&mut **transaction)
+        .await?;
+        Ok((reservation.map(|row| row.token), protection))
+    }
+    /// Replacing only the given datastrings of the stored transfer, in one transaction.
+    ///
+    /// The owner and the PIN are checked again with the rows locked like at [`Self::insert_or_update`],
+    /// they stay as they are.
+    pub async fn merge_update(
+        &self,
+        datas: TransferDatas,
+        token: Option<&str>,
+        pin_checked: Option<&str>,
+        snapshots: u32,
+    ) -> Result<TransferWrite, Box<dyn Error + Send + Sync>> {
+        let accountx = datas.accountx.parse::<u32>()?;
+        let mut transaction = self.pool.begin().await?;
+        let (reservation, protection) = Self::lock_access(&mut transaction, accountx).await?;
+        if protection.is_none() {
+            return Ok(TransferWrite::NotFound);
+        }
+        if let Err(denied) = check_access(
+            reservation.as_deref(),
+            protection.as_ref(),
+            token,
+            pin_checked,
+        ) {
+            return Ok(denied);
+        }
+        let row = sqlx::query!(
+            "SELECT `data` FROM `transfer` WHERE `accountx`= ? FOR UPDATE",
+            accountx
+        )
+        .fetch_one(&mut *transaction)
+        .await?;
+        let mut merged = self
+            .crypto
+            .decrypt(&datas.accountx, &row.data)
+            .and_then(|data| TransferDatas::from_stored(&data))?;
+        merged.merge(datas);
+        let (data, key_id) = self
+            .crypto
+            .encrypt(&merged.accountx, &merged.to_stored()?)?;
+        Self::snapshot(&mut transaction, accountx, snapshots).await?;
+        sqlx::query!(
+            "UPDATE `transfer` SET `data`=?, `key_id`=?, `used`=0, `regdate`=CURRENT_TIMESTAMP() WHERE `accountx`=?",
+            data,
+            key_id,
+            accountx
+        )
+        .execute(&mut *transaction)
+        .await?;
+        transaction.commit().await?;
+        Ok(TransferWrite::Written)
+    }
+    /// Saving the current `data` as a snapshot and removing the ones over the last `snapshots`.
+    async fn snapshot(
+        transaction: &mut Transaction<'_, MySql>,
+        accountx: u32,
+        snapshots: u32,
+    ) -> Result<(), sqlx::Error> {
+        if snapshots == 0 {
+            return Ok(());
+        }
+        sqlx::query!(
+            "INSERT INTO `transfer_snapshot`(`accountx`, `data`, `key_id`) SELECT `accountx`, `data`, `key_id` FROM `transfer` WHERE `accountx`= ?",
+            accountx
+        )
+        .execute(&mut **transaction)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM `transfer_snapshot` WHERE `accountx`= ? AND `id` NOT IN (SELECT `id` FROM (SELECT `id` FROM `transfer_snapshot` WHERE `accountx`= ? ORDER BY `id` DESC LIMIT ?) AS `kept`)",
+            accountx,
+            accountx,
+            snapshots
+        )
+        .execute(&mut **transaction)
+        .await?;
+        Ok(())
+    }
+    /// Removing the snapshots of the transfers which were removed *(expired or used up)*.
+    pub async fn delete_orphan_snapshots(&self) -> Result<MySqlQueryResult, sqlx::Error> {
+        sqlx::query!(
+            "DELETE FROM `transfer_snapshot` WHERE NOT EXISTS (SELECT 1 FROM `transfer` WHERE `transfer`.`accountx` = `transfer_snapshot`.`accountx`)"
+        )
+        .execute(&self.pool as &MySqlPool)
+        .await
+    }
+    /// The snapshots of the transfer, the newest first.
+    pub async fn get_snapshots(
+        &self,
+        accountx: u32,
+    ) -> Result<Vec<TransferSnapshotRow>, sqlx::Error> {
+        sqlx::query_as!(
+            TransferSnapshotRow,
+            "SELECT `id`, `accountx`, `data`, `key_id`, `regdate` FROM `transfer_snapshot` WHERE `accountx`= ? ORDER BY `id` DESC",
+            accountx
+        )
+        .fetch_all(&self.pool as &MySqlPool)
+        .await
+    }
+    /// Decrypting and decoding a snapshot.
+    pub fn decode_snapshot(&self, snapshot: &TransferSnapshotRow) -> Result<TransferDatas, String> {
+        self.crypto
+            .decrypt(&snapshot.accountx.to_string(), &snapshot.data)
+            .and_then(|data| TransferDatas::from_stored(&data))
+    }
+    /// Putting back the snapshot `id`, the current `data` becomes a snapshot too *(so it can be undone)*.
+    ///
+    /// The PIN and the owner stay. Returning `false` if the transfer has no such snapshot.
+    pub async fn restore_snapshot(
+        &self,
+        accountx: u32,
+        id: u64,
+        snapshots: u32,
+    ) -> Result<bool, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let Some(snapshot) = sqlx::query_as!(
+            TransferSnapshotRow,
+            "SELECT `id`, `accountx`, `data`, `key_id`, `regdate` FROM `transfer_snapshot` WHERE `id`= ? AND `accountx`= ?",
+            id,
+            accountx
+        )
+        .fetch_optional(&mut *transaction)
+        .await?
+        else {
+            return Ok(false);
+        };
+        // even without snapshots, so the restore can be undone
+        Self::snapshot(&mut transaction, accountx, snapshots.max(1)).await?;
+        sqlx::query!(
+            "INSERT INTO `transfer`(`accountx`, `data`, `key_id`) VALUES (?,?,?) ON DUPLICATE KEY UPDATE `data`=?, `key_id`=?, `used`=0, `regdate`=CURRENT_TIMESTAMP()",
+            accountx,
+            snapshot.data,
+            snapshot.key_id,
+            snapshot.data,
+            snapshot.key_id
+        )
+        .execute(&mut *transaction)
+        .await?;
+        transaction.commit().await?;
+        Ok(true)
+    }
+    /// Reserving the `accountx` with the hashed token, unless it's reserved or registered.
+    ///
+    /// Returning `false` if the number is taken.
+    pub async fn reserve(&self, accountx: u32, token_hash: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            "INSERT IGNORE INTO `transfer_reservation`(`accountx`, `token`) SELECT ?, ? FROM DUAL WHERE NOT EXISTS (SELECT 1 FROM `transfer` WHERE `accountx`= ?)",
+            accountx,
+            token_hash,
+            accountx
+        )
+        .execute(&self.pool as &MySqlPool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+    /// Hashed token of the reservation, `None` if the number isn't reserved.
+    pub async fn get_reservation(&self, accountx: u32) -> Result<Option<String>, sqlx::Error> {
+        let row = sqlx::query!(
+            "SELECT `token` FROM `transfer_reservation` WHERE `accountx`= ?",
+            accountx
+        )
+        .fetch_optional(&self.pool as &MySqlPool)
+        .await?;
+        Ok(row.map(|row| row.token))
+    }
+    pub async fn delete_reservations_older(
+        &self,
+        regdate: DateTime<Utc>,
+    ) -> Result<MySqlQueryResult, sqlx::Error> {
+        sqlx::query!(
+            "DELETE FROM `transfer_reservation` WHERE `regdate` < ?",
+            regdate
+        )
+        .execute(&self.pool as &MySqlPool)
+        .await
+    }
+    /// The rows in plaintext or encrypted with an old key *(`key_id` is the active key)*, paged by `accountx`.
+    pub async fn get_unmigrated(
+        &self,
+        key_id: &str,
+        after_accountx: u32,
+        limit: u32,
+    ) -> Result<Vec<StoredTransferData>, sqlx::Error> {
+        sqlx::query_as!(
+            StoredTransferData,
+            "SELECT CAST(NULL AS UNSIGNED) AS `id`, `accountx`, `data` FROM `transfer` WHERE (`key_id` IS NULL OR `key_id` <> ?) AND `accountx` > ? ORDER BY `accountx` LIMIT ?",
+            key_id,
+            after_accountx,
+            limit
+        )
+        .fetch_all(&self.pool as &MySqlPool)
+        .await
+    }
+    /// The snapshots in plaintext or encrypted with an old key, paged by `id`.
+    pub async fn get_unmigrated_snapshots(
+        &self,
+        key_id: &str,
+        after_id: u64,
+        limit: u32,
+    ) -> Result<Vec<StoredTransferData>, sqlx::Error> {
+        sqlx::query_as!(
+            StoredTransferData,
+            "SELECT `id` AS `id?`, `accountx`, `data` FROM `transfer_snapshot` WHERE (`key_id` IS NULL OR `key_id` <> ?) AND `id` > ? ORDER BY `id` LIMIT ?",
+            key_id,
+            after_id,
+            limit
+        )
+        .fetch_all(&self.pool as &MySqlPool)
+        .await
+    }
+    /// Encrypting a row again with the active key, unless it was changed since reading it.
+    ///
+    /// Returning `false` if the row was changed.
+    pub async fn reencrypt(
+        &self,
+        stored: &StoredTransferData,
+    ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let accountx = stored.accountx.to_string();
+        let data = String::from_utf8(self.crypto.decrypt(&accountx, &stored.data)?)?;
+        let (new_data, key_id) = self.crypto.encrypt(&accountx, &data)?;
+        let result = match stored.id {
+            Some(id) => {
+                sqlx::query!(
+                    "UPDATE `transfer_snapshot` SET `data`=?, `key_id`=? WHERE `id`=? AND `data`=?",
+                    new_data,
+                    key_id,
+                    id,
+                    stored.data
+                )
+                .execute(&self.pool as &MySqlPool)
+                .await?
+            }
+            None => {
+                sqlx::query!(
+                    "UPDATE `transfer` SET `data`=?, `key_id`=? WHERE `accountx`=? AND `data`=?",
+                    new_data,
+                    key_id,
+                    stored.accountx,
+                    stored.data
+                )
+                .execute(&self.pool as &MySqlPool)
+                .await?
+            }
+        };
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::de::value::{Error as ValueError, MapDeserializer};
+
+    #[test]
+    fn transfer_datas_json_test() {
+        let json = r#"{"accountx":"123456789","datastring1":"1|2","datastring9":"code","datastring20":""}"#;
+        let datas: TransferDatas = serde_json::from_str(json).unwrap();
+        assert_eq!(datas.accountx, "123456789");
+        assert_eq!(datas.datastring(1), "1|2");
+        assert_eq!(datas.datastring(2), "");
+        assert_eq!(serde_json::to_string(&datas).unwrap(), json);
+    }
+
+    #[test]
+    fn transfer_datas_form_test() {
+        let pairs = vec![
+            ("systemCall", "register"),
+            ("accountx", "123456789"),
+            ("datastring19", "3|0"),
+            ("datastring99", "unknown slot"),
+        ];
+        let deserializer = MapDeserializer::<_, ValueError>::new(pairs.into_iter());
+        let datas = TransferDatas::deserialize(deserializer).unwrap();
+        assert_eq!(datas.datastrings.len(), 1);
+        assert_eq!(datas.datastring(19), "3|0");
+
+        let deserializer =
+            MapDeserializer::<_, ValueError>::new(vec![("datastring1", "")].into_iter());
+        assert!(TransferDatas::deserialize(deserializer).is_err());
+    }
+
+    #[test]
+    fn transfer_datas_given_test() {
+        let mut datas = TransferDatas {
+            accountx: "123456789".to_owned(),
+            ..Default::default()
+        };
+        datas.datastrings.insert(19, "3|0|12".to_owned());
+        // the characters are missing, only the partial update is valid
+        assert!(datas.is_invalid().is_some());
+        assert!(datas.is_invalid_given().is_none());
+
+        // the other slots aren't checked
+        datas.datastrings.insert(1, "1|on|2".to_owned());
+        assert!(datas.is_invalid_given().is_none());
+
+        datas.datastrings.insert(9, "not a character".to_owned());
+        assert!(datas
+            .is_invalid_given()
+            .is_some_and(|error| error.contains("datastring9")));
+    }
+
+    #[test]
+    fn transfer_access_test() {
+        let token = "owner-token";
+        let hash = transfer_owner::hash_token(token);
+        let protection = |pin: Option<&str>, owner: Option<&str>| TransferProtection {
+            pin: pin.map(|pin| pin.to_owned()),
+            owner_token: owner.map(|owner| owner.to_owned()),
+        };
+
+        assert_eq!(check_access(None, None, None, None), Ok(()));
+        assert_eq!(check_access(Some(&hash), None, Some(token), None), Ok(()));
+        assert_eq!(
+            check_access(Some(&hash), None, Some("other"), None),
+            Err(TransferWrite::NotOwner)
+        );
+        let owned = protection(None, Some(&hash));
+        assert_eq!(check_access(None, Some(&owned), Some(token), None), Ok(()));
+        assert_eq!(
+            check_access(None, Some(&owned), None, None),
+            Err(TransferWrite::NotOwner)
+        );
+
+        // the PIN changed between the check and the write
+        let protected = protection(Some("hash-1"), None);
+        assert_eq!(
+            check_access(None, Some(&protected), None, Some("hash-1")),
+            Ok(())
+        );
+        assert_eq!(
+            check_access(None, Some(&protected), None, Some("hash-0")),
+            Err(TransferWrite::PinChanged)
+        );
+        assert_eq!(
+            check_access(None, Some(&protected), None, None),
+            Err(TransferWrite::PinChanged)
+        );
+        assert_eq!(
+            check_access(None, Some(&protection(None, None)), None, Some("hash-1")),
+            Err(TransferWrite::PinChanged)
+        );
+    }
+
+    #[test]
+    fn transfer_datas_merge_test() {
+        let mut datas = TransferDatas {
+            accountx: "123456789".to_owned(),
+            ..Default::default()
+        };
+        datas.datastrings.insert(1, "1|2".to_owned());
+        datas.datastrings.insert(2, "kept".to_owned());
+        datas.datastrings.insert(3, "cleared".to_owned());
+
+        let mut update = TransferDatas {
+            accountx: "123456789".to_owned(),
+            ..Default::default()
+        };
+        update.datastrings.insert(1, "3|4".to_owned());
+        update.datastrings.insert(3, String::new());
+        datas.merge(update);
+        assert_eq!(datas.datastring(1), "3|4");
+        assert_eq!(datas.datastring(2), "kept");
+        assert_eq!(datas.datastrings.get(&3).map(|s| s.as_str()), Some(""));
+        assert_eq!(datas.datastrings.len(), 3);
+    }
+
+    #[test]
+    fn transfer_datas_stored_test() {
+        let datas = TransferDatas::from_stored(br#"{"accountx":"123456789","datastring9":"code"}"#)
+            .unwrap();
+        let stored = datas.to_stored().unwrap();
+        assert_eq!(
+            TransferDatas::from_stored(stored.as_bytes()).unwrap(),
+            datas
+        );
+
+        // rows like the serializer wrote them
+        let legacy = TransferDatas::from_stored(
+            br#"{"accountx":"123456789","datastring1":"1|2","datastring9":"code"}"#,
+        )
+        .unwrap();
+        assert_eq!(legacy.accountx, "123456789");
+        assert_eq!(legacy.datastring(1), "1|2");
+        assert_eq!(legacy.datastring(9), "code");
+        assert_eq!(legacy.datastrings.len(), 2);
+        // unknown keys, like the `version` some rows were stored with, are ignored
+        assert_eq!(
+            TransferDatas::from_stored(br#"{"version":2,"accountx":"123456789"}"#)
+                .unwrap()
+                .accountx,
+            "123456789"
+        );
+
+        // corrupt rows
+        assert!(TransferDatas::from_stored(b"{\"accountx\":").is_err());
+        assert!(TransferDatas::from_stored(b"[]").is_err());
+        assert!(TransferDatas::from_stored(br#"{"datastring1":""}"#).is_err());
+        assert!(
+            TransferDatas::from_stored(br#"{"accountx":"123456789","datastring1":5}"#).is_err()
+        );
     }
 }
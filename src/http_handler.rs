@@ -1,23 +1,29 @@
-use std::collections::HashMap;
 use std::sync::Arc;
-use std::time::{Duration, Instant};
 
 use axum::response::Redirect;
 use axum::{middleware, routing, Router};
 use chrono::{DateTime, Utc};
-use tokio::sync::{Mutex, RwLock};
+use inline_colorization::*;
+use tokio::sync::Mutex;
 use tower_http::services::ServeDir;
 
 use crate::enviorment;
+use crate::free_oc_cache::FreeOcCache;
 use crate::gachaplus_database::short_log_table::ShortLog;
+use crate::latest_version::{LatestVersionCache, VersionOffers};
+use crate::oc_ranking::{self, OcRanking};
+use crate::random_selector::RandomSelector;
+use crate::release_files::{self, ReleaseIndex};
+use crate::settings::Settings;
+use crate::transfer_pin::PinAttempts;
 
-use self::middlewares::ratelimit::create_ratelimit;
+use self::middlewares::ratelimit::{create_ratelimit, RateLimitCache};
 
-use super::gachaplus_database::free_oc_table::FreeOc;
 use super::gachaplus_database::GachaPlusDatabase;
 
 mod ip_manager;
 mod middlewares;
+mod password_manager;
 mod response_manager;
 use middlewares::*;
 mod handlers;
@@ -25,17 +31,37 @@ use handlers::*;
 
 pub struct AppState {
     pub database: GachaPlusDatabase,
-    pub oc_chache: RwLock<Vec<FreeOc>>,
+    pub oc_chache: FreeOcCache,
     pub log_queue: Mutex<Vec<ShortLog>>,
-    pub rate_limit: HashMap<&'static str, (Mutex<HashMap<String, Instant>>, Duration)>,
+    pub oc_ranking: OcRanking,
+    pub random_selector: RandomSelector,
+    pub settings: Settings,
+    pub pin_attempts: PinAttempts,
+    pub latest_versions: LatestVersionCache,
+    pub version_offers: VersionOffers,
+    pub release_index: ReleaseIndex,
+    pub rate_limit: RateLimitCache,
     pub startup_time: DateTime<Utc>,
+    #[cfg_attr(debug_assertions, allow(dead_code))]
     pub request_protection: bool,
 }
 impl AppState {
     pub async fn new(database_url: String) -> Arc<Self> {
         let database = GachaPlusDatabase::new(database_url).await;
-        let oc_chache = RwLock::new(Vec::new());
+        let oc_chache = FreeOcCache::default();
         let log_queue = Mutex::new(Vec::new());
+        let oc_ranking = load_oc_ranking(&database).await;
+        let random_selector = RandomSelector::default();
+        let settings = load_settings(&database).await;
+        database
+            .oc_table
+            .cache
+            .configure_from_settings(&settings)
+            .await;
+        let pin_attempts = PinAttempts::default();
+        let latest_versions = LatestVersionCache::default();
+        let version_offers = VersionOffers::default();
+        let release_index = load_release_index(&database, &latest_versions).await;
         let rate_limit = create_ratelimit();
         let startup_time = Utc::now();
         let request_protection = enviorment::get_enviorment("PROTECTION").contains('1');
@@ -43,6 +69,13 @@ impl AppState {
             database,
             oc_chache,
             log_queue,
+            oc_ranking,
+            random_selector,
+            settings,
+            pin_attempts,
+            latest_versions,
+            version_offers,
+            release_index,
             rate_limit,
             startup_time,
             request_protection,
@@ -51,9 +84,106 @@ impl AppState {
     }
 }
 
+async fn load_oc_ranking(database: &GachaPlusDatabase) -> OcRanking {
+    let oc_ranking = OcRanking::default();
+    let since = oc_ranking::current_hour().saturating_sub(oc_ranking::KEPT_HOURS);
+    match (
+        database
+            .oc_ranking_table
+            .get_all_time_top(oc_ranking::ALL_TIME_KEPT)
+            .await,
+        database.oc_ranking_table.get_since(since).await,
+    ) {
+        (Ok(all_time), Ok(hours)) => {
+            oc_ranking.load(all_time, hours).await;
+            println!("{color_cyan}{}{color_green}\tRanking: ✅ Loading the OC ranking snapshot is successful! ✅{color_white}",
+                Utc::now().format("[%H:%M:%S]"),
+            );
+        }
+        (Err(err), _) | (_, Err(err)) => {
+            println!(
+                "{color_red}{}\tRanking: 🔥 Failed to load the OC ranking snapshot: {:?} 🔥{color_white}",
+                Utc::now().format("[%H:%M:%S]"),
+                err
+            );
+            std::process::exit(1);
+        }
+    }
+    oc_ranking
+}
+
+async fn load_settings(database: &GachaPlusDatabase) -> Settings {
+    let settings = Settings::default();
+    match database.settings_table.get_all().await {
+        Ok(values) => {
+            settings.replace(values).await;
+            println!("{color_cyan}{}{color_green}\tSettings: ✅ Loading the settings is successful! ✅{color_white}",
+                Utc::now().format("[%H:%M:%S]"),
+            );
+            for error in settings.invalid().await {
+                println!(
+                    "{color_yellow}{}\tSettings: {error}, the default is used{color_white}",
+                    Utc::now().format("[%H:%M:%S]"),
+                );
+            }
+        }
+        Err(err) => {
+            println!(
+                "{color_red}{}\tSettings: 🔥 Failed to load the settings: {:?} 🔥{color_white}",
+                Utc::now().format("[%H:%M:%S]"),
+                err
+            );
+            std::process::exit(1);
+        }
+    }
+    settings
+}
+
+/// Verifying the hosted release files against the checksums of their rows, before they're served.
+///
+/// *(A corrupt file is logged and not served, the server still starts.)*
+async fn load_release_index(
+    database: &GachaPlusDatabase,
+    latest_versions: &LatestVersionCache,
+) -> ReleaseIndex {
+    let release_index = ReleaseIndex::default();
+    match database.latestversion_table.get_versions().await {
+        Ok(rows) => {
+            let corrupt = release_index.verify(&rows).await;
+            latest_versions.store(rows).await;
+            for name in corrupt.iter() {
+                println!(
+                    "{color_red}{}\tReleases: 🔥 Checksum mismatch, not serving the corrupt release file: {} 🔥{color_white}",
+                    Utc::now().format("[%H:%M:%S]"),
+                    name
+                );
+            }
+            let (verified, _) = release_index.counts().await;
+            println!("{color_cyan}{}{color_green}\tReleases: ✅ Verifying the release files is successful! ({} verified, {} corrupt) ✅{color_white}",
+                Utc::now().format("[%H:%M:%S]"),
+                verified,
+                corrupt.len()
+            );
+        }
+        Err(err) => {
+            println!(
+                "{color_red}{}\tReleases: 🔥 Failed to load the releases: {:?} 🔥{color_white}",
+                Utc::now().format("[%H:%M:%S]"),
+                err
+            );
+            std::process::exit(1);
+        }
+    }
+    release_index
+}
+
 pub async fn create_router(app_state: Arc<AppState>) -> Router {
     Router::new()
         .nest_service("/files", ServeDir::new("files"))
+        .route(
+            &format!("/{}/{{name}}", release_files::RELEASE_DIR),
+            routing::get(release::download_release),
+        )
         .route(
             "/",
             routing::any(Redirect::permanent(
@@ -62,6 +192,52 @@ pub async fn create_router(app_state: Arc<AppState>) -> Router {
         )
         .route("/info", routing::get(stat::get_info))
         .route("/test", routing::get(hello_world::get_hello_world))
+        .route(
+            "/settings",
+            routing::get(settings::get_settings).post(settings::set_setting),
+        )
+        .route("/freeoc", routing::get(free_oc::get_ocs))
+        .route("/freeoc/add", routing::post(free_oc::add_oc))
+        .route("/freeoc/remove", routing::post(free_oc::remove_oc))
+        .route("/freeoc/reorder", routing::post(free_oc::reorder_ocs))
+        .route(
+            "/freeoc/users",
+            routing::get(free_oc::get_users).post(free_oc::set_user),
+        )
+        .route("/oc_of_the_day", routing::get(oc_of_the_day::get_daily_oc))
+        .route(
+            "/oc_of_the_day/pin",
+            routing::post(oc_of_the_day::set_daily_pin),
+        )
+        .route("/moderation", routing::get(moderation::get_moderation))
+        .route(
+            "/moderation/action",
+            routing::post(moderation::moderation_action),
+        )
+        .route(
+            "/transfer/snapshots",
+            routing::get(transfer_snapshot::get_snapshots),
+        )
+        .route(
+            "/transfer/snapshots/restore",
+            routing::post(transfer_snapshot::restore_snapshot),
+        )
+        .route("/release", routing::get(release::get_releases))
+        .route("/release/publish", routing::post(release::publish_release))
+        .route(
+            "/release/rollback",
+            routing::post(release::rollback_release),
+        )
+        .route("/release/rollout", routing::post(release::set_rollout))
+        .route("/ranking", routing::get(ranking::get_ranking))
+        .route(
+            "/random_ocs",
+            routing::get(random_character::get_random_ocs),
+        )
+        .route(
+            "/ranking/exclude",
+            routing::post(ranking::set_ranking_exclude),
+        )
         .route(
             "/GPscripts/latestversion_and_checksum.php",
             routing::any(version::verion_and_checksum),
@@ -74,6 +250,10 @@ pub async fn create_router(app_state: Arc<AppState>) -> Router {
             "/GPscripts/randomcode.php",
             routing::post(random_character::get_random_oc),
         )
+        .route(
+            "/GPscripts/dailycode.php",
+            routing::post(oc_of_the_day::get_daily_oc_legacy),
+        )
         .route(
             "/GPscripts/club_import.php",
             routing::post(character::get_oc),
@@ -82,6 +262,14 @@ pub async fn create_router(app_state: Arc<AppState>) -> Router {
             "/GPscripts/club_export.php",
             routing::post(character::add_oc),
         )
+        .route(
+            "/GPscripts/club_ranking_optout.php",
+            routing::post(ranking::set_ranking_optout),
+        )
+        .route(
+            "/GPscripts/club_report.php",
+            routing::post(moderation::report_oc),
+        )
         .route(
             "/GPscripts/club_login.php",
             routing::post(transfer_datas::get_transfer_datas),
@@ -90,6 +278,14 @@ pub async fn create_router(app_state: Arc<AppState>) -> Router {
             "/GPscripts/club_register.php",
             routing::post(transfer_datas::add_transfer_datas),
         )
+        .route(
+            "/GPscripts/club_update.php",
+            routing::post(transfer_datas::update_transfer_datas),
+        )
+        .route(
+            "/GPscripts/club_allocate.php",
+            routing::post(transfer_datas::allocate_accountx),
+        )
         .with_state(app_state.clone())
         .layer(middleware::from_fn_with_state(
             app_state.clone(),
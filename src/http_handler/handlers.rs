@@ -1,7 +1,27 @@
 pub mod character;
+pub mod free_oc;
 pub mod hello_world;
+pub mod moderation;
+pub mod oc_of_the_day;
 pub mod random_character;
+pub mod ranking;
+pub mod release;
+pub mod settings;
 pub mod startup;
 pub mod stat;
 pub mod transfer_datas;
+pub mod transfer_snapshot;
 pub mod version;
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+
+fn database_error(error: sqlx::Error) -> Response {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        format!("Database error: {error}"),
+    )
+        .into_response()
+}
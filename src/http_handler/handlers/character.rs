@@ -1,14 +1,14 @@
-use std::sync::Arc;
+use std::{net::SocketAddr, sync::Arc};
 
 use axum::{
-    extract::State,
-    http::StatusCode,
+    extract::{ConnectInfo, State},
+    http::{HeaderMap, StatusCode},
     response::{IntoResponse, Response},
     Form,
 };
 use serde::Deserialize;
 
-use crate::http_handler::{response_manager::ResponseManager, AppState};
+use crate::http_handler::{ip_manager, response_manager::ResponseManager, AppState};
 use crate::{character_code::CharacterCode, gachaplus_database::oc_table::Oc};
 
 #[derive(Deserialize)]
@@ -26,6 +26,8 @@ pub struct OcAddParam {
 #[axum::debug_handler]
 pub async fn get_oc(
     State(app_state): State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Form(param): Form<OcGetParam>,
 ) -> Response {
     let accountx = param.accountx.to_uppercase().trim().to_owned();
@@ -36,12 +38,16 @@ pub async fn get_oc(
         )
             .into_response();
     }
+    // the ranking counts an IP once per OC in an hour
+    let importer = ip_manager::ip_to_long(&ip_manager::get_user_ip(addr, headers));
 
     //free ocs cache
     {
-        let reader = app_state.oc_chache.read().await;
-        let free_oc_res = reader.iter().find(|item| item.accountx == accountx);
-        if let Some(free_oc) = free_oc_res {
+        let snapshot = app_state.oc_chache.load().await;
+        if let Some(free_oc) = snapshot.get_including_hidden(&accountx) {
+            if let Some(importer) = importer {
+                app_state.oc_ranking.record(&accountx, importer).await;
+            }
             return ResponseManager::new_ok()
                 .add("xmycode", &free_oc.mycode)
                 .into_response();
@@ -52,9 +58,14 @@ pub async fn get_oc(
     let oc_result = app_state.database.oc_table.get_oc(&accountx).await;
 
     match oc_result {
-        Ok(oc) => ResponseManager::new_ok()
-            .add("xmycode", &oc.mycode)
-            .into_response(),
+        Ok(oc) => {
+            if let Some(importer) = importer {
+                app_state.oc_ranking.record(&accountx, importer).await;
+            }
+            ResponseManager::new_ok()
+                .add("xmycode", &oc.mycode)
+                .into_response()
+        }
         Err(_) => (StatusCode::BAD_REQUEST, "No result").into_response(),
     }
 }
@@ -99,18 +110,36 @@ pub async fn add_oc(
         .insert_or_update_oc(oc.clone())
         .await;
 
-    if res.is_ok() {
-        ResponseManager::new_ok().into_response()
-    } else {
-        (
+    match res {
+        Ok(_) => ResponseManager::new_ok().into_response(),
+        Err(error) => (
             StatusCode::BAD_REQUEST,
-            format!("Upload error: {:?}", res.unwrap_err()),
+            format!("Upload error: {:?}", error),
         )
-            .into_response()
+            .into_response(),
     }
 }
 
-fn is_id(id: &str) -> bool {
+/// The `secretid` of the OC, from the free OCs *(also the hidden ones)* or from the `oc` table.
+pub async fn get_secretid(app_state: &AppState, accountx: &str) -> Option<String> {
+    if let Some(free_oc) = app_state
+        .oc_chache
+        .load()
+        .await
+        .get_including_hidden(accountx)
+    {
+        return Some(free_oc.secretid.to_owned());
+    }
+    app_state
+        .database
+        .oc_table
+        .get_oc(accountx)
+        .await
+        .ok()
+        .map(|oc| oc.secretid)
+}
+
+pub fn is_id(id: &str) -> bool {
     id.chars()
         .all(|c| c.is_numeric() || c.is_ascii_uppercase() || c == '#' || c == '$')
 }
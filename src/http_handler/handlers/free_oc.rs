@@ -0,0 +1,300 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Form, Json,
+};
+use chrono::Utc;
+use serde::Deserialize;
+
+use crate::{
+    background_jobs::random_character_cache::refresh_free_oc_cache,
+    character_code::CharacterCode,
+    gachaplus_database::free_oc_table::FreeOc,
+    http_handler::{password_manager, AppState},
+};
+
+use super::{character::is_id, database_error};
+
+#[derive(Deserialize)]
+pub struct PasswordParam {
+    password: Option<String>,
+}
+#[derive(Deserialize)]
+pub struct UserParam {
+    password: Option<String>,
+    /// Missing `id` creates a new owner
+    id: Option<u64>,
+    name: String,
+    link: Option<String>,
+}
+#[derive(Deserialize)]
+pub struct AddOcParam {
+    password: Option<String>,
+    accountx: String,
+    owner: u64,
+    boost: Option<u8>,
+    position: Option<u32>,
+    /// Missing `mycode` copies the OC from the `oc` table
+    mycode: Option<String>,
+    secretid: Option<String>,
+}
+#[derive(Deserialize)]
+pub struct RemoveOcParam {
+    password: Option<String>,
+    accountx: String,
+}
+#[derive(Deserialize)]
+pub struct ReorderParam {
+    password: Option<String>,
+    /// Comma separated `accountx`s in the new order
+    accountxs: String,
+}
+
+#[axum::debug_handler]
+pub async fn get_users(
+    State(app_state): State<Arc<AppState>>,
+    Query(param): Query<PasswordParam>,
+) -> Response {
+    if !password_manager::is_valid_password(&param.password) {
+        return (StatusCode::UNAUTHORIZED, "Bad password").into_response();
+    }
+    match app_state.database.oc_random_table.get_users().await {
+        Ok(users) => Json(users).into_response(),
+        Err(error) => database_error(error),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn set_user(
+    State(app_state): State<Arc<AppState>>,
+    Form(param): Form<UserParam>,
+) -> Response {
+    if !password_manager::is_valid_password(&param.password) {
+        return (StatusCode::UNAUTHORIZED, "Bad password").into_response();
+    }
+    let name = param.name.trim();
+    let link = param.link.unwrap_or_default();
+    if name.is_empty() || name.len() > 64 || link.len() > 512 {
+        return (StatusCode::BAD_REQUEST, "Invalid `name` or `link` length!").into_response();
+    }
+
+    let table = &app_state.database.oc_random_table;
+    if let Some(id) = param.id {
+        if let Some(response) = check_owner(&app_state, id).await {
+            return response;
+        }
+    }
+    let res = match param.id {
+        Some(id) => table.update_user(id, name, &link).await.map(|_| id),
+        None => table.insert_user(name, &link).await,
+    };
+    match res {
+        Ok(id) => (StatusCode::OK, id.to_string()).into_response(),
+        Err(error) => database_error(error),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn get_ocs(
+    State(app_state): State<Arc<AppState>>,
+    Query(param): Query<PasswordParam>,
+) -> Response {
+    if !password_manager::is_valid_password(&param.password) {
+        return (StatusCode::UNAUTHORIZED, "Bad password").into_response();
+    }
+    Json(app_state.oc_chache.load().await.ocs()).into_response()
+}
+
+/// Adding a free OC, or editing it if it's already in the pool.
+#[axum::debug_handler]
+pub async fn add_oc(
+    State(app_state): State<Arc<AppState>>,
+    Form(param): Form<AddOcParam>,
+) -> Response {
+    if !password_manager::is_valid_password(&param.password) {
+        return (StatusCode::UNAUTHORIZED, "Bad password").into_response();
+    }
+    let accountx = param.accountx.to_uppercase().trim().to_owned();
+    if !is_id(&accountx) || accountx.len() != 7 {
+        return (StatusCode::BAD_REQUEST, "Invalid `accountx`").into_response();
+    }
+
+    let (secretid, mycode) = match (param.mycode, param.secretid) {
+        (Some(mycode), Some(secretid)) => {
+            let secretid = secretid.to_uppercase().trim().to_owned();
+            if !is_id(&secretid) || secretid.len() != 9 {
+                return (StatusCode::BAD_REQUEST, "Invalid `secretid`").into_response();
+            }
+            match CharacterCode::new_from_code(&mycode) {
+                Ok(character) => (secretid, character.to_code()),
+                Err(err) => {
+                    return (StatusCode::BAD_REQUEST, format!("Invalid `mycode`: {err}"))
+                        .into_response()
+                }
+            }
+        }
+        (None, None) => match app_state.database.oc_table.get_oc(&accountx).await {
+            Ok(oc) => (oc.secretid, oc.mycode),
+            Err(_) => return (StatusCode::BAD_REQUEST, "No result").into_response(),
+        },
+        _ => {
+            return (
+                StatusCode::BAD_REQUEST,
+                "`mycode` and `secretid` must be given together",
+            )
+                .into_response()
+        }
+    };
+
+    if let Some(response) = check_owner(&app_state, param.owner).await {
+        return response;
+    }
+
+    let position = match param.position {
+        Some(position) => position,
+        None => {
+            let snapshot = app_state.oc_chache.load().await;
+            match snapshot.get_including_hidden(&accountx) {
+                Some(oc) => oc.position,
+                None => next_position(snapshot.ocs()),
+            }
+        }
+    };
+
+    let oc = FreeOc {
+        accountx,
+        owner: param.owner,
+        secretid,
+        mycode,
+        boost: param.boost.unwrap_or(0),
+        position,
+        createdate: Utc::now(),
+        updatedate: Utc::now(),
+    };
+    if let Err(error) = app_state
+        .database
+        .oc_random_table
+        .insert_or_update_oc(&oc)
+        .await
+    {
+        return database_error(error);
+    }
+    refreshed_response(&app_state).await
+}
+
+#[axum::debug_handler]
+pub async fn remove_oc(
+    State(app_state): State<Arc<AppState>>,
+    Form(param): Form<RemoveOcParam>,
+) -> Response {
+    if !password_manager::is_valid_password(&param.password) {
+        return (StatusCode::UNAUTHORIZED, "Bad password").into_response();
+    }
+    let accountx = param.accountx.to_uppercase().trim().to_owned();
+    match app_state
+        .database
+        .oc_random_table
+        .delete_oc(&accountx)
+        .await
+    {
+        Ok(res) if res.rows_affected() == 0 => {
+            (StatusCode::BAD_REQUEST, "No result").into_response()
+        }
+        Ok(_) => refreshed_response(&app_state).await,
+        Err(error) => database_error(error),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn reorder_ocs(
+    State(app_state): State<Arc<AppState>>,
+    Form(param): Form<ReorderParam>,
+) -> Response {
+    if !password_manager::is_valid_password(&param.password) {
+        return (StatusCode::UNAUTHORIZED, "Bad password").into_response();
+    }
+    let Some(accountxs) = parse_accountxs(&param.accountxs) else {
+        return (StatusCode::BAD_REQUEST, "Invalid `accountxs`").into_response();
+    };
+
+    match app_state.database.oc_random_table.reorder(&accountxs).await {
+        Ok(_) => refreshed_response(&app_state).await,
+        Err(error) => database_error(error),
+    }
+}
+
+/// Applying the changes in `oc_chache` now instead of waiting for the next refresh.
+async fn refreshed_response(app_state: &AppState) -> Response {
+    match refresh_free_oc_cache(app_state).await {
+        Ok(ocs_len) => (StatusCode::OK, ocs_len.to_string()).into_response(),
+        Err(error) => database_error(error),
+    }
+}
+
+/// Error response if there is no owner with the `id` *(instead of the foreign key error of the insert)*.
+async fn check_owner(app_state: &AppState, id: u64) -> Option<Response> {
+    match app_state.database.oc_random_table.get_user(id).await {
+        Ok(Some(_)) => None,
+        Ok(None) => Some((StatusCode::BAD_REQUEST, "No owner with this `id`").into_response()),
+        Err(error) => Some(database_error(error)),
+    }
+}
+
+/// Position after the last free OC.
+fn next_position(ocs: &[FreeOc]) -> u32 {
+    ocs.iter().map(|oc| oc.position).max().unwrap_or(0) + 1
+}
+
+/// The comma separated `accountx`s, `None` if any of them is invalid.
+fn parse_accountxs(accountxs: &str) -> Option<Vec<String>> {
+    let accountxs: Vec<String> = accountxs
+        .split(',')
+        .map(|accountx| accountx.to_uppercase().trim().to_owned())
+        .filter(|accountx| !accountx.is_empty())
+        .collect();
+    accountxs
+        .iter()
+        .all(|accountx| is_id(accountx) && accountx.len() == 7)
+        .then_some(accountxs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn free_oc(accountx: &str, position: u32) -> FreeOc {
+        FreeOc {
+            accountx: accountx.to_owned(),
+            owner: 1,
+            secretid: "SECRETID0".to_owned(),
+            mycode: String::new(),
+            boost: 0,
+            position,
+            createdate: Utc::now(),
+            updatedate: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn free_oc_position_test() {
+        assert_eq!(next_position(&[]), 1);
+        assert_eq!(
+            next_position(&[free_oc("AAAAAAA", 3), free_oc("BBBBBBB", 7)]),
+            8
+        );
+    }
+
+    #[test]
+    fn free_oc_reorder_param_test() {
+        assert_eq!(
+            parse_accountxs("aaaaaaa, BBBBBBB,,"),
+            Some(vec!["AAAAAAA".to_owned(), "BBBBBBB".to_owned()])
+        );
+        assert_eq!(parse_accountxs(""), Some(Vec::new()));
+        assert_eq!(parse_accountxs("AAAAAAA,BBBB"), None);
+        assert_eq!(parse_accountxs("AAAAAA!"), None);
+    }
+}
@@ -0,0 +1,246 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use axum::{
+    extract::{ConnectInfo, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{self, IntoResponse, Response},
+    Form,
+};
+use build_html::*;
+use chrono::Utc;
+use serde::Deserialize;
+
+use crate::{
+    background_jobs::random_character_cache::refresh_free_oc_cache,
+    gachaplus_database::oc_moderation_table::{ModerationActionEnum, ModerationStatusEnum},
+    http_handler::{ip_manager, password_manager, response_manager::ResponseManager, AppState},
+};
+
+use super::{
+    character::{get_secretid, is_id},
+    database_error,
+    ranking::refresh_excluded,
+};
+
+/// Default count of reports hiding an OC *(can be changed with the `report_hide_threshold` setting, `0` turns it off)*
+const DEFAULT_HIDE_THRESHOLD: u64 = 5;
+const MAX_REASON_LEN: usize = 255;
+/// Posting the action for the OC chosen in the queue *(the radio buttons belong to the form by `form='moderation'`)*
+const ACTION_FORM: &str = "<form id='moderation' method='post' action='/moderation/action'>\
+    <input type='password' name='password' placeholder='Password' required> \
+    <button name='action' value='approve'>Approve</button> \
+    <button name='action' value='hide'>Hide</button> \
+    <button name='action' value='delete'>Delete</button>\
+    </form>";
+
+#[derive(Deserialize)]
+pub struct ReportParam {
+    accountx: String,
+    reason: String,
+}
+#[derive(Deserialize)]
+pub struct ModerationParam {
+    password: Option<String>,
+}
+#[derive(Deserialize)]
+pub struct ModerationActionParam {
+    password: Option<String>,
+    accountx: String,
+    action: String,
+}
+
+#[axum::debug_handler]
+pub async fn report_oc(
+    State(app_state): State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Form(param): Form<ReportParam>,
+) -> Response {
+    let accountx = param.accountx.to_uppercase().trim().to_owned();
+    let reason = param.reason.trim();
+    if !is_id(&accountx) || accountx.len() != 7 {
+        return (StatusCode::BAD_REQUEST, "Invalid `accountx`").into_response();
+    }
+    if reason.is_empty() || reason.chars().count() > MAX_REASON_LEN {
+        return (StatusCode::BAD_REQUEST, "Invalid `reason`").into_response();
+    }
+    let ip = ip_manager::get_user_ip(addr, headers);
+    let Some(reporter) = ip_manager::ip_to_long(&ip) else {
+        return (StatusCode::BAD_REQUEST, "Invalid IP").into_response();
+    };
+    // the free OCs handed out by `randomcode.php` can be reported too
+    if get_secretid(&app_state, &accountx).await.is_none() {
+        return (StatusCode::BAD_REQUEST, "No result").into_response();
+    }
+
+    let table = &app_state.database.oc_moderation_table;
+    if let Err(error) = table.insert_report(&accountx, reason, reporter).await {
+        return database_error(error);
+    }
+
+    let threshold = app_state
+        .settings
+        .get("report_hide_threshold", DEFAULT_HIDE_THRESHOLD)
+        .await;
+    let reports = table.count_reports(&accountx).await.unwrap_or_default();
+    if threshold > 0 && reports >= threshold {
+        // the decisions of the moderators aren't overwritten
+        if let Ok(None) = table.get_status(&accountx).await {
+            // `0` marks the automatic decisions
+            let res = apply(&app_state, &accountx, ModerationActionEnum::AutoHide, 0).await;
+            if let Err(error) = res {
+                return database_error(error);
+            }
+        }
+    }
+
+    ResponseManager::new_ok().into_response()
+}
+
+#[axum::debug_handler]
+pub async fn get_moderation(
+    State(app_state): State<Arc<AppState>>,
+    Query(param): Query<ModerationParam>,
+) -> response::Html<String> {
+    if !password_manager::is_valid_password(&param.password) {
+        return response::Html(String::from("<h1 align='center'>Bad password</h1>"));
+    }
+    moderation_page(&app_state, None).await
+}
+
+#[axum::debug_handler]
+pub async fn moderation_action(
+    State(app_state): State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Form(param): Form<ModerationActionParam>,
+) -> Response {
+    if !password_manager::is_valid_password(&param.password) {
+        return (StatusCode::UNAUTHORIZED, "Bad password").into_response();
+    }
+    let accountx = param.accountx.to_uppercase().trim().to_owned();
+    if !is_id(&accountx) || accountx.len() != 7 {
+        return (StatusCode::BAD_REQUEST, "Invalid `accountx`").into_response();
+    }
+    let action = match param.action.as_str() {
+        "approve" => ModerationActionEnum::Approve,
+        "hide" => ModerationActionEnum::Hide,
+        "delete" => ModerationActionEnum::Delete,
+        _ => return (StatusCode::BAD_REQUEST, "Invalid `action`").into_response(),
+    };
+    let ip = ip_manager::get_user_ip(addr, headers);
+    let Some(moderator) = ip_manager::ip_to_long(&ip) else {
+        return (StatusCode::BAD_REQUEST, "Invalid IP").into_response();
+    };
+
+    if let Err(error) = apply(&app_state, &accountx, action, moderator).await {
+        return database_error(error);
+    }
+
+    // the page is sent back instead of a redirect, so the password isn't put into a URL
+    let message = format!("{} {accountx}: done", param.action);
+    moderation_page(&app_state, Some(&message))
+        .await
+        .into_response()
+}
+
+/// The moderation queue and the audit log, with a form posting the actions *(the password has to be typed in)*.
+async fn moderation_page(app_state: &AppState, message: Option<&str>) -> response::Html<String> {
+    let table = &app_state.database.oc_moderation_table;
+    let mut queue_table: Vec<[String; 5]> = Vec::new();
+    for oc in table.get_reported().await.unwrap_or_default() {
+        let status = match oc.status.and_then(ModerationStatusEnum::from_value) {
+            Some(ModerationStatusEnum::Approved) => "Approved",
+            Some(ModerationStatusEnum::Hidden) => "Hidden",
+            None => "Pending",
+        };
+        queue_table.push([
+            format!(
+                "<label><input type='radio' form='moderation' name='accountx' value='{0}' required> <b>{0}</b></label>",
+                escape_html(&oc.accountx)
+            ),
+            oc.reports.to_string(),
+            escape_html(&oc.reasons.unwrap_or_default()).replace('\n', "<br>"),
+            oc.last_report
+                .map(|date| date.format("%Y.%m.%d. %H:%M:%S").to_string())
+                .unwrap_or_default(),
+            status.to_owned(),
+        ]);
+    }
+
+    let mut log_table: Vec<[String; 5]> = Vec::new();
+    for log in table.get_logs().await.unwrap_or_default() {
+        let action = match ModerationActionEnum::from_value(log.action) {
+            Some(ModerationActionEnum::Approve) => "Approve",
+            Some(ModerationActionEnum::Hide) => "Hide",
+            Some(ModerationActionEnum::Delete) => "Delete",
+            Some(ModerationActionEnum::AutoHide) => "Automatic hide",
+            None => "Unknown",
+        };
+        log_table.push([
+            log.regdate.format("%Y.%m.%d. %H:%M:%S").to_string(),
+            format!("<b>{}</b>", log.accountx),
+            action.to_owned(),
+            log.reports.to_string(),
+            format!("{:08x}", log.moderator),
+        ]);
+    }
+
+    let mut html_page = HtmlPage::new()
+        .with_title("Gacha Plus")
+        .with_header(1, "Moderation")
+        .with_header(3, Utc::now().format("%Y.%m.%d. %H:%M:%S (UTC)"));
+    if let Some(message) = message {
+        html_page.add_paragraph(escape_html(message));
+    }
+    html_page.add_container(
+        Container::new(ContainerType::Main)
+            .with_header(2, "Reported OCs")
+            .with_table(Table::from(queue_table).with_header_row([
+                "Accountx",
+                "Reports",
+                "Reasons",
+                "Last report",
+                "Status",
+            ]))
+            .with_raw(ACTION_FORM)
+            .with_header(2, "Audit log")
+            .with_table(Table::from(log_table).with_header_row([
+                "Date",
+                "Accountx",
+                "Action",
+                "Reports",
+                "Moderator",
+            ])),
+    );
+    // absolute paths, because the page is also the answer of `/moderation/action`
+    html_page.add_head_link("/files/style.css", "stylesheet");
+    html_page.add_head_link_attr("/files/icon.png", "icon", [("type", "image/png")]);
+
+    response::Html(html_page.to_html_string())
+}
+
+/// Applying the moderation action in one transaction, then refreshing the caches.
+async fn apply(
+    app_state: &AppState,
+    accountx: &str,
+    action: ModerationActionEnum,
+    moderator: u32,
+) -> Result<(), sqlx::Error> {
+    let database = &app_state.database;
+    let table = &database.oc_moderation_table;
+    match action {
+        ModerationActionEnum::Approve => table.approve(accountx, moderator).await?,
+        // the hidden OCs can still be imported by their `accountx` *(the hidden free OCs are kept aside in `oc_chache` for that)*
+        ModerationActionEnum::Hide | ModerationActionEnum::AutoHide => {
+            table.hide(accountx, action, moderator).await?
+        }
+        ModerationActionEnum::Delete => {
+            table.delete(accountx, moderator).await?;
+            database.oc_table.cache.invalidate(accountx).await;
+        }
+    }
+    refresh_excluded(app_state).await;
+    refresh_free_oc_cache(app_state).await?;
+    Ok(())
+}
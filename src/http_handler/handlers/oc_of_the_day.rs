@@ -0,0 +1,143 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Form, Json,
+};
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    http_handler::{password_manager, response_manager::ResponseManager, AppState},
+    oc_of_the_day::daily_pick,
+};
+
+use super::character::is_id;
+
+#[derive(Serialize)]
+pub struct DailyOc {
+    day: NaiveDate,
+    accountx: String,
+    mycode: String,
+    pinned: bool,
+}
+#[derive(Deserialize)]
+pub struct PinParam {
+    password: Option<String>,
+    day: NaiveDate,
+    /// Empty or missing `accountx` removes the pin
+    accountx: Option<String>,
+}
+
+#[axum::debug_handler]
+pub async fn get_daily_oc_legacy(State(app_state): State<Arc<AppState>>) -> Response {
+    match find_daily_oc(&app_state, Utc::now().date_naive()).await {
+        Some(daily_oc) => ResponseManager::new_ok()
+            .add("accountx", &daily_oc.accountx)
+            .add("xmycode", &daily_oc.mycode)
+            .into_response(),
+        None => (StatusCode::INTERNAL_SERVER_ERROR, "No character result").into_response(),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn get_daily_oc(State(app_state): State<Arc<AppState>>) -> Response {
+    match find_daily_oc(&app_state, Utc::now().date_naive()).await {
+        Some(daily_oc) => Json(daily_oc).into_response(),
+        None => (StatusCode::INTERNAL_SERVER_ERROR, "No character result").into_response(),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn set_daily_pin(
+    State(app_state): State<Arc<AppState>>,
+    Form(param): Form<PinParam>,
+) -> Response {
+    if !password_manager::is_valid_password(&param.password) {
+        return (StatusCode::UNAUTHORIZED, "Bad password").into_response();
+    }
+    let accountx = param
+        .accountx
+        .unwrap_or_default()
+        .to_uppercase()
+        .trim()
+        .to_owned();
+
+    let table = &app_state.database.oc_of_the_day_table;
+    let res = if accountx.is_empty() {
+        table.delete_pin(param.day).await
+    } else {
+        if !is_id(&accountx) || accountx.len() != 7 {
+            return (StatusCode::BAD_REQUEST, "Invalid `accountx`").into_response();
+        }
+        table.set_pin(param.day, &accountx).await
+    };
+
+    match res {
+        Ok(_) => (StatusCode::OK, "Ok").into_response(),
+        Err(error) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Database error: {error}"),
+        )
+            .into_response(),
+    }
+}
+
+/// The pinned or the stored OC of the `day` if there is one, otherwise the next pick from the free OCs.
+///
+/// *(The pick is stored with its cycle, so the following days skip it.)*
+async fn find_daily_oc(app_state: &AppState, day: NaiveDate) -> Option<DailyOc> {
+    let table = &app_state.database.oc_of_the_day_table;
+    let snapshot = app_state.oc_chache.load().await;
+    let stored = table.get_day(day).await.ok()?;
+    if let Some(row) = &stored {
+        let mycode = match (snapshot.get(&row.accountx), row.cycle) {
+            (Some(free_oc), _) => Some(free_oc.mycode.to_owned()),
+            // the pins can be any OC, the picks only the visible free OCs
+            (None, None) => app_state
+                .database
+                .oc_table
+                .get_oc(&row.accountx)
+                .await
+                .ok()
+                .map(|oc| oc.mycode),
+            (None, Some(_)) => None,
+        };
+        if let Some(mycode) = mycode {
+            return Some(DailyOc {
+                day,
+                accountx: row.accountx.to_owned(),
+                mycode,
+                pinned: row.cycle.is_none(),
+            });
+        }
+    }
+
+    let cycle = table.get_last_cycle().await.ok()?;
+    let featured = table.get_cycle(cycle).await.ok()?;
+    let pool: Vec<&str> = snapshot
+        .ocs()
+        .iter()
+        .map(|oc| oc.accountx.as_str())
+        .collect();
+    let (accountx, cycle) = daily_pick(day, &pool, cycle, &featured)?;
+    if stored.is_none() {
+        match table.insert_pick(day, accountx, cycle).await {
+            // picked by an other request in the meantime
+            Ok(result) if result.rows_affected() == 0 => {
+                return Box::pin(find_daily_oc(app_state, day)).await
+            }
+            Ok(_) => (),
+            Err(_) => return None,
+        }
+    }
+    let oc = snapshot.get(accountx)?;
+    Some(DailyOc {
+        day,
+        accountx: oc.accountx.to_owned(),
+        mycode: oc.mycode.to_owned(),
+        pinned: false,
+    })
+}
@@ -1,20 +1,52 @@
-use std::sync::Arc;
+use std::{net::SocketAddr, sync::Arc};
 
 use axum::{
-    extract::State,
-    http::StatusCode,
+    extract::{ConnectInfo, Query, State},
+    http::{HeaderMap, StatusCode},
     response::{IntoResponse, Response},
+    Json,
 };
-use rand::seq::SliceRandom;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
 
-use super::super::{response_manager::ResponseManager, AppState};
+use crate::{gachaplus_database::free_oc_table::FreeOc, random_selector::SelectorConfig};
+
+use super::super::{ip_manager, response_manager::ResponseManager, AppState};
+
+/// Default cap of `count` *(can be changed with the `random_batch_max` setting)*
+const DEFAULT_BATCH_MAX: usize = 50;
+
+#[derive(Deserialize)]
+pub struct RandomOcsParam {
+    count: Option<usize>,
+    owner: Option<u64>,
+    /// Only the OCs created on this day or later *(`YYYY-MM-DD`)*
+    since: Option<NaiveDate>,
+}
+#[derive(Serialize)]
+pub struct RandomOcEntry {
+    accountx: String,
+    mycode: String,
+    owner: u64,
+    createdate: DateTime<Utc>,
+}
 
 #[axum::debug_handler]
-pub async fn get_random_oc(State(app_state): State<Arc<AppState>>) -> Response {
-    let reader = app_state.oc_chache.read().await;
+pub async fn get_random_oc(
+    State(app_state): State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> Response {
+    let ip = ip_manager::get_user_ip(addr, headers);
+    let client = ip_manager::ip_to_long(&ip);
+    let config = SelectorConfig::from_settings(&app_state.settings).await;
 
-    let mut rng = rand::thread_rng();
-    let random_oc = reader.choose(&mut rng);
+    let snapshot = app_state.oc_chache.load().await;
+    let ocs: Vec<&FreeOc> = snapshot.ocs().iter().collect();
+    let random_oc = app_state
+        .random_selector
+        .choose(client, &ocs, &config)
+        .await;
 
     match random_oc {
         Some(oc) => ResponseManager::new_ok()
@@ -25,3 +57,87 @@ pub async fn get_random_oc(State(app_state): State<Arc<AppState>>) -> Response {
         None => (StatusCode::INTERNAL_SERVER_ERROR, "No character result").into_response(),
     }
 }
+
+/// A batch of random free OCs, chosen like `randomcode.php` *(weighted, without the recently served ones)*.
+#[axum::debug_handler]
+pub async fn get_random_ocs(
+    State(app_state): State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Query(param): Query<RandomOcsParam>,
+) -> Response {
+    let max_count = app_state
+        .settings
+        .get("random_batch_max", DEFAULT_BATCH_MAX)
+        .await;
+    let count = param.count.unwrap_or(1).min(max_count);
+    let ip = ip_manager::get_user_ip(addr, headers);
+    let client = ip_manager::ip_to_long(&ip);
+    let config = SelectorConfig::from_settings(&app_state.settings).await;
+
+    let snapshot = app_state.oc_chache.load().await;
+    let filtered = filter_ocs(snapshot.ocs(), param.owner, param.since);
+    let random_ocs: Vec<RandomOcEntry> = app_state
+        .random_selector
+        .choose_multiple(client, &filtered, count, &config)
+        .await
+        .into_iter()
+        .map(|oc| RandomOcEntry {
+            accountx: oc.accountx.to_owned(),
+            mycode: oc.mycode.to_owned(),
+            owner: oc.owner,
+            createdate: oc.createdate,
+        })
+        .collect();
+
+    Json(random_ocs).into_response()
+}
+
+/// The free OCs of the `owner`, created on the `since` day or later *(only referenced, the picked ones are copied)*.
+fn filter_ocs(ocs: &[FreeOc], owner: Option<u64>, since: Option<NaiveDate>) -> Vec<&FreeOc> {
+    ocs.iter()
+        .filter(|oc| owner.is_none_or(|owner| oc.owner == owner))
+        .filter(|oc| since.is_none_or(|since| oc.createdate.date_naive() >= since))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::*;
+
+    fn free_oc(accountx: &str, owner: u64, age_days: i64) -> FreeOc {
+        FreeOc {
+            accountx: accountx.to_owned(),
+            owner,
+            secretid: "SECRETID0".to_owned(),
+            mycode: String::new(),
+            boost: 0,
+            position: 0,
+            createdate: Utc::now() - TimeDelta::days(age_days),
+            updatedate: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn random_filter_test() {
+        let ocs = vec![
+            free_oc("AAAAAAA", 1, 0),
+            free_oc("BBBBBBB", 2, 0),
+            free_oc("CCCCCCC", 1, 30),
+        ];
+        let accountxs = |owner, since| -> Vec<String> {
+            filter_ocs(&ocs, owner, since)
+                .into_iter()
+                .map(|oc| oc.accountx.to_owned())
+                .collect()
+        };
+        assert_eq!(accountxs(None, None).len(), 3);
+        assert_eq!(accountxs(Some(1), None), ["AAAAAAA", "CCCCCCC"]);
+        let week_ago = (Utc::now() - TimeDelta::days(7)).date_naive();
+        assert_eq!(accountxs(None, Some(week_ago)), ["AAAAAAA", "BBBBBBB"]);
+        assert_eq!(accountxs(Some(1), Some(week_ago)), ["AAAAAAA"]);
+        assert!(accountxs(Some(3), None).is_empty());
+    }
+}
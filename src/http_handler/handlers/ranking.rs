@@ -0,0 +1,127 @@
+use std::{collections::HashSet, sync::Arc};
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Form, Json,
+};
+use serde::Deserialize;
+
+use crate::{
+    gachaplus_database::oc_ranking_table::ExcludeReasonEnum,
+    http_handler::{password_manager, response_manager::ResponseManager, AppState},
+    oc_ranking::RankingWindow,
+};
+
+use super::character::{get_secretid, is_id};
+
+const DEFAULT_LIMIT: usize = 10;
+const MAX_LIMIT: usize = 100;
+
+#[derive(Deserialize)]
+pub struct RankingParam {
+    window: Option<RankingWindow>,
+    limit: Option<usize>,
+}
+#[derive(Deserialize)]
+pub struct OptOutParam {
+    accountx: String,
+    secretid: String,
+    optout: u8,
+}
+#[derive(Deserialize)]
+pub struct ExcludeParam {
+    password: Option<String>,
+    accountx: String,
+    exclude: bool,
+}
+
+#[axum::debug_handler]
+pub async fn get_ranking(
+    State(app_state): State<Arc<AppState>>,
+    Query(param): Query<RankingParam>,
+) -> Response {
+    let window = param.window.unwrap_or(RankingWindow::Week);
+    let limit = param.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+
+    Json(app_state.oc_ranking.top(window, limit).await).into_response()
+}
+
+#[axum::debug_handler]
+pub async fn set_ranking_optout(
+    State(app_state): State<Arc<AppState>>,
+    Form(param): Form<OptOutParam>,
+) -> Response {
+    let accountx = param.accountx.to_uppercase().trim().to_owned();
+    let secretid = param.secretid.to_uppercase().trim().to_owned();
+    if !is_id(&accountx) || accountx.len() != 7 {
+        return (StatusCode::BAD_REQUEST, "Invalid `accountx`").into_response();
+    }
+
+    // the free OCs can opt out too
+    match get_secretid(&app_state, &accountx).await {
+        Some(oc_secretid) if oc_secretid == secretid => (),
+        Some(_) => return (StatusCode::BAD_REQUEST, "No access").into_response(),
+        None => return (StatusCode::BAD_REQUEST, "No result").into_response(),
+    }
+
+    let table = &app_state.database.oc_ranking_table;
+    let res = if param.optout == 1 {
+        table.exclude(&accountx, ExcludeReasonEnum::OptOut).await
+    } else {
+        table.include(&accountx, ExcludeReasonEnum::OptOut).await
+    };
+    if let Err(error) = res {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Database error: {error}"),
+        )
+            .into_response();
+    }
+    refresh_excluded(&app_state).await;
+
+    ResponseManager::new_ok().into_response()
+}
+
+#[axum::debug_handler]
+pub async fn set_ranking_exclude(
+    State(app_state): State<Arc<AppState>>,
+    Form(param): Form<ExcludeParam>,
+) -> Response {
+    if !password_manager::is_valid_password(&param.password) {
+        return (StatusCode::UNAUTHORIZED, "Bad password").into_response();
+    }
+    let accountx = param.accountx.to_uppercase().trim().to_owned();
+    if !is_id(&accountx) || accountx.len() != 7 {
+        return (StatusCode::BAD_REQUEST, "Invalid `accountx`").into_response();
+    }
+
+    let table = &app_state.database.oc_ranking_table;
+    let res = if param.exclude {
+        table.exclude(&accountx, ExcludeReasonEnum::Moderated).await
+    } else {
+        table.include(&accountx, ExcludeReasonEnum::Moderated).await
+    };
+    match res {
+        Ok(_) => {
+            refresh_excluded(&app_state).await;
+            (StatusCode::OK, "Ok").into_response()
+        }
+        Err(error) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Database error: {error}"),
+        )
+            .into_response(),
+    }
+}
+
+/// Applying the exclusion changes now instead of waiting for the next snapshot.
+pub async fn refresh_excluded(app_state: &AppState) {
+    if let Ok(excluded) = app_state.database.oc_ranking_table.get_excluded().await {
+        app_state
+            .oc_ranking
+            .set_excluded(excluded.into_iter().collect::<HashSet<String>>())
+            .await;
+    }
+}
@@ -0,0 +1,329 @@
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Form, Json,
+};
+use serde::Deserialize;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_util::io::ReaderStream;
+
+use crate::{
+    background_jobs::latest_version_cache::{get_latest_versions, refresh_latest_versions},
+    client_version::ClientVersion,
+    gachaplus_database::latestversion_table::NewRelease,
+    http_handler::{password_manager, AppState},
+    latest_version::{self, BETA_CHANNEL, STABLE_CHANNEL},
+    release_files::{self, ByteRange, ReleaseFileState},
+};
+
+use super::database_error;
+
+#[derive(Deserialize)]
+pub struct PasswordParam {
+    password: Option<String>,
+}
+#[derive(Deserialize)]
+pub struct PublishParam {
+    password: Option<String>,
+    version: String,
+    /// Name of the uploaded file *(only its extension is kept)*
+    filename: String,
+    platform: Option<String>,
+    xbits: Option<u8>,
+    channel: Option<String>,
+    /// Percent of the clients offered the release *(`100` if missing)*
+    rollout: Option<u8>,
+}
+#[derive(Deserialize)]
+pub struct RollbackParam {
+    password: Option<String>,
+    id: u32,
+}
+#[derive(Deserialize)]
+pub struct RolloutParam {
+    password: Option<String>,
+    id: u32,
+    rollout: u8,
+}
+
+/// Listing the releases, the newest first.
+#[axum::debug_handler]
+pub async fn get_releases(
+    State(app_state): State<Arc<AppState>>,
+    Query(param): Query<PasswordParam>,
+) -> Response {
+    if !password_manager::is_valid_password(&param.password) {
+        return (StatusCode::UNAUTHORIZED, "Bad password").into_response();
+    }
+    match app_state.database.latestversion_table.get_versions().await {
+        Ok(rows) => Json(rows).into_response(),
+        Err(error) => database_error(error),
+    }
+}
+
+/// Publishing the release in the body: storing the binary under `releases/` and inserting its row with the checksum.
+///
+/// *(The body is only read after the password and the parameters are checked, and it's streamed into the file.)*
+#[axum::debug_handler]
+pub async fn publish_release(
+    State(app_state): State<Arc<AppState>>,
+    Query(param): Query<PublishParam>,
+    body: Body,
+) -> Response {
+    if !password_manager::is_valid_password(&param.password) {
+        return (StatusCode::UNAUTHORIZED, "Bad password").into_response();
+    }
+    let version = param.version.trim();
+    if version.parse::<ClientVersion>().is_err() {
+        return (StatusCode::BAD_REQUEST, "Invalid `version`").into_response();
+    }
+    let platform = match param.platform.as_deref().map(str::trim) {
+        None | Some("") => None,
+        Some(platform) => match latest_version::normalize_platform(platform) {
+            Some(platform) => Some(platform),
+            None => return (StatusCode::BAD_REQUEST, "Invalid `platform`").into_response(),
+        },
+    };
+    if param.xbits.is_some_and(|xbits| xbits != 32 && xbits != 64) {
+        return (StatusCode::BAD_REQUEST, "Invalid `xbits`").into_response();
+    }
+    let channel = match param.channel.as_deref().map(str::trim) {
+        None | Some("") => STABLE_CHANNEL,
+        Some(channel) if channel == STABLE_CHANNEL || channel == BETA_CHANNEL => channel,
+        Some(_) => return (StatusCode::BAD_REQUEST, "Invalid `channel`").into_response(),
+    };
+    let rollout = param.rollout.unwrap_or(100);
+    if rollout > 100 {
+        return (StatusCode::BAD_REQUEST, "Invalid `rollout`").into_response();
+    }
+    let name = match release_files::release_file_name(
+        version,
+        platform,
+        param.xbits,
+        channel,
+        &param.filename,
+    ) {
+        Ok(name) => name,
+        Err(error) => return (StatusCode::BAD_REQUEST, error).into_response(),
+    };
+
+    let stored = release_files::store_release(
+        std::path::Path::new(release_files::RELEASE_DIR),
+        &name,
+        body,
+        release_files::MAX_RELEASE_SIZE,
+    )
+    .await;
+    let (path, checksum) = match stored {
+        Ok(stored) => stored,
+        Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists => {
+            return (StatusCode::CONFLICT, error.to_string()).into_response()
+        }
+        Err(error) if error.kind() == std::io::ErrorKind::FileTooLarge => {
+            return (StatusCode::PAYLOAD_TOO_LARGE, error.to_string()).into_response()
+        }
+        Err(error) if error.kind() == std::io::ErrorKind::InvalidInput => {
+            return (StatusCode::BAD_REQUEST, error.to_string()).into_response()
+        }
+        Err(error) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error at storing the release: {error}"),
+            )
+                .into_response()
+        }
+    };
+    let base_url = app_state
+        .settings
+        .get("release_base_url", "https://gacha-plus.com".to_owned())
+        .await;
+    let url = format!(
+        "{}/{}/{name}",
+        base_url.trim_end_matches('/'),
+        release_files::RELEASE_DIR
+    );
+    match app_state
+        .database
+        .latestversion_table
+        .insert(&NewRelease {
+            version,
+            checksum: &checksum,
+            url: &url,
+            platform,
+            xbits: param.xbits,
+            channel,
+            rollout,
+        })
+        .await
+    {
+        Ok(id) => {
+            // served only once both the binary and its row are there
+            app_state
+                .release_index
+                .insert(name, ReleaseFileState::Verified(checksum.clone()))
+                .await;
+            let _ = refresh_latest_versions(&app_state).await;
+            (StatusCode::OK, format!("{id}|{checksum}|{url}")).into_response()
+        }
+        Err(error) => {
+            // no row, no binary
+            let _ = tokio::fs::remove_file(&path).await;
+            database_error(error)
+        }
+    }
+}
+
+/// Changing the rollout percent of a release *(`100` offers it to everyone)*.
+#[axum::debug_handler]
+pub async fn set_rollout(
+    State(app_state): State<Arc<AppState>>,
+    Form(param): Form<RolloutParam>,
+) -> Response {
+    if !password_manager::is_valid_password(&param.password) {
+        return (StatusCode::UNAUTHORIZED, "Bad password").into_response();
+    }
+    if param.rollout > 100 {
+        return (StatusCode::BAD_REQUEST, "Invalid `rollout`").into_response();
+    }
+    match app_state
+        .database
+        .latestversion_table
+        .set_rollout(param.id, param.rollout)
+        .await
+    {
+        Ok(result) if result.rows_affected() == 0 => {
+            (StatusCode::BAD_REQUEST, "No result").into_response()
+        }
+        Ok(_) => {
+            let _ = refresh_latest_versions(&app_state).await;
+            (StatusCode::OK, format!("{}%", param.rollout)).into_response()
+        }
+        Err(error) => database_error(error),
+    }
+}
+
+/// Rolling back a release: marking its row, so the previous release of its target is served again.
+///
+/// *(The row is kept for the history. Its binary isn't served anymore, but it stays on the disk, so the
+/// downloads in progress can finish.)*
+#[axum::debug_handler]
+pub async fn rollback_release(
+    State(app_state): State<Arc<AppState>>,
+    Form(param): Form<RollbackParam>,
+) -> Response {
+    if !password_manager::is_valid_password(&param.password) {
+        return (StatusCode::UNAUTHORIZED, "Bad password").into_response();
+    }
+    match app_state
+        .database
+        .latestversion_table
+        .rollback(param.id)
+        .await
+    {
+        Ok(result) if result.rows_affected() == 0 => {
+            (StatusCode::BAD_REQUEST, "No result").into_response()
+        }
+        Ok(_) => {
+            let _ = refresh_latest_versions(&app_state).await;
+            let rows = get_latest_versions(&app_state).await;
+            let name = rows
+                .as_deref()
+                .and_then(|rows| rows.iter().find(|row| row.id == param.id))
+                .and_then(|row| release_files::release_name_from_url(&row.url));
+            if let Some(name) = name {
+                app_state.release_index.remove(name).await;
+            }
+            (StatusCode::OK, "Rolled back").into_response()
+        }
+        Err(error) => database_error(error),
+    }
+}
+
+/// Downloading a hosted release, resumable with `Range` requests.
+///
+/// *(Only the files verified against their checksum are served, the `ETag` is the checksum.)*
+#[axum::debug_handler]
+pub async fn download_release(
+    State(app_state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    let checksum = match app_state.release_index.get(&name).await {
+        Some(ReleaseFileState::Verified(checksum)) => checksum,
+        Some(ReleaseFileState::Corrupt) => {
+            return (StatusCode::SERVICE_UNAVAILABLE, "Release file is corrupt").into_response()
+        }
+        None => return (StatusCode::NOT_FOUND, "No release file").into_response(),
+    };
+    let etag = format!("\"{checksum}\"");
+    let header_str = |name| headers.get(name).and_then(|value| value.to_str().ok());
+    if header_str(header::IF_NONE_MATCH).is_some_and(|tags| {
+        tags.split(',')
+            .any(|tag| tag.trim() == etag || tag.trim() == "*")
+    }) {
+        return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response();
+    }
+
+    let mut file = match tokio::fs::File::open(release_files::release_path(&name)).await {
+        Ok(file) => file,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            return (StatusCode::NOT_FOUND, "No release file").into_response()
+        }
+        Err(error) => return file_error(error),
+    };
+    let len = match file.metadata().await {
+        Ok(metadata) => metadata.len(),
+        Err(error) => return file_error(error),
+    };
+    // a range of an other version is the whole file
+    let range = match header_str(header::IF_RANGE) {
+        Some(if_range) if if_range.trim() != etag => ByteRange::Full,
+        _ => release_files::parse_range(header_str(header::RANGE), len),
+    };
+    let (status, start, end) = match range {
+        ByteRange::Full => (StatusCode::OK, 0, len.saturating_sub(1)),
+        ByteRange::Partial(start, end) => (StatusCode::PARTIAL_CONTENT, start, end),
+        ByteRange::Unsatisfiable => {
+            return (
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                [(header::CONTENT_RANGE, format!("bytes */{len}"))],
+            )
+                .into_response()
+        }
+    };
+    let content_len = if len == 0 { 0 } else { end - start + 1 };
+    if let Err(error) = file.seek(std::io::SeekFrom::Start(start)).await {
+        return file_error(error);
+    }
+    // streamed, the binaries aren't loaded into the memory
+    let body = Body::from_stream(ReaderStream::new(file.take(content_len)));
+
+    let mut response = Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/octet-stream")
+        .header(header::CONTENT_LENGTH, content_len)
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::ETAG, etag)
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{name}\""),
+        );
+    if status == StatusCode::PARTIAL_CONTENT {
+        response = response.header(header::CONTENT_RANGE, format!("bytes {start}-{end}/{len}"));
+    }
+    response.body(body).unwrap_or_else(|error| {
+        (StatusCode::INTERNAL_SERVER_ERROR, error.to_string()).into_response()
+    })
+}
+
+fn file_error(error: std::io::Error) -> Response {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        format!("Error at reading the release: {error}"),
+    )
+        .into_response()
+}
@@ -0,0 +1,78 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Form, Json,
+};
+use serde::Deserialize;
+
+use crate::{
+    http_handler::{password_manager, AppState},
+    settings,
+};
+
+use super::database_error;
+
+#[derive(Deserialize)]
+pub struct SettingsParam {
+    password: Option<String>,
+}
+#[derive(Deserialize)]
+pub struct SetSettingParam {
+    password: Option<String>,
+    name: String,
+    /// Empty value deletes the setting *(so the default is used)*
+    value: String,
+}
+
+/// Listing the settings.
+#[axum::debug_handler]
+pub async fn get_settings(
+    State(app_state): State<Arc<AppState>>,
+    Query(param): Query<SettingsParam>,
+) -> Response {
+    if !password_manager::is_valid_password(&param.password) {
+        return (StatusCode::UNAUTHORIZED, "Bad password").into_response();
+    }
+
+    Json(app_state.settings.all().await).into_response()
+}
+
+/// Changing a setting, after checking that its value parses as the type it's read as.
+#[axum::debug_handler]
+pub async fn set_setting(
+    State(app_state): State<Arc<AppState>>,
+    Form(param): Form<SetSettingParam>,
+) -> Response {
+    if !password_manager::is_valid_password(&param.password) {
+        return (StatusCode::UNAUTHORIZED, "Bad password").into_response();
+    }
+
+    let name = param.name.trim();
+    let value = param.value.trim();
+    if name.is_empty() || name.len() > 64 || value.len() > 512 {
+        return (StatusCode::BAD_REQUEST, "Invalid `name` or `value` length!").into_response();
+    }
+    if !value.is_empty() {
+        if let Err(error) = settings::validate(name, value) {
+            return (StatusCode::BAD_REQUEST, error).into_response();
+        }
+    }
+    let table = &app_state.database.settings_table;
+    let res = if value.is_empty() {
+        table.delete(name).await
+    } else {
+        table.set(name, value).await
+    };
+    if let Err(error) = res {
+        return database_error(error);
+    }
+    match table.get_all().await {
+        Ok(values) => app_state.settings.replace(values).await,
+        Err(error) => return database_error(error),
+    }
+
+    Json(app_state.settings.all().await).into_response()
+}
@@ -1,14 +1,20 @@
 use std::sync::Arc;
 
 use axum::{
-    extract::State,
-    http::StatusCode,
+    extract::{OriginalUri, State},
+    http::{HeaderMap, StatusCode},
     response::{IntoResponse, Response},
     Form,
 };
 use serde::Deserialize;
 
-use crate::http_handler::{response_manager::ResponseManager, AppState};
+use crate::{
+    client_version::{self, ClientVersion},
+    http_handler::{
+        middlewares::fake_request::update_response, response_manager::ResponseManager, AppState,
+    },
+    latest_version::ReleaseTarget,
+};
 
 #[derive(Debug, Deserialize)]
 pub struct StartupInput {
@@ -20,23 +26,34 @@ pub struct StartupInput {
 #[axum::debug_handler]
 pub async fn startup_request(
     State(app_state): State<Arc<AppState>>,
+    OriginalUri(path): OriginalUri,
+    headers: HeaderMap,
     Form(input): Form<StartupInput>,
 ) -> Response {
-    if input.version.len() > 7 || input.platform.len() > 7 {
-        return (
-            StatusCode::BAD_REQUEST,
-            "Invalid `version` or `platform` length!",
-        )
-            .into_response();
+    if input.platform.len() > 7 {
+        return (StatusCode::BAD_REQUEST, "Invalid `platform` length!").into_response();
     }
+    let Ok(version) = input.version.parse::<ClientVersion>() else {
+        return (StatusCode::BAD_REQUEST, "Invalid `version`").into_response();
+    };
+    let user_agent = headers
+        .get("User-Agent")
+        .and_then(|user_agent| user_agent.to_str().ok());
+    let target = ReleaseTarget::new(Some(&input.platform), Some(input.xbits), None, user_agent);
     let req = app_state
         .database
         .startup_log_table
-        .insert(input.platform, input.version, input.xbits)
+        .insert(input.platform, version.to_string(), input.xbits)
         .await;
     if let Err(error) = req {
         return (StatusCode::INTERNAL_SERVER_ERROR, error.to_string()).into_response();
     }
+    // the old clients send their version only here
+    if let Some(min_version) = client_version::min_version(&app_state.settings, path.path()).await {
+        if version < min_version {
+            return update_response(&app_state, &target).await;
+        }
+    }
 
     ResponseManager::new_ok().into_response()
 }
@@ -1,4 +1,5 @@
 use std::{
+    cmp::Reverse,
     collections::HashMap,
     sync::Arc,
     time::{Duration, Instant},
@@ -9,14 +10,14 @@ use axum::{
     response,
 };
 use build_html::*;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use memory_stats::memory_stats;
 use serde::Deserialize;
 use sysinfo::{Component, Components, Disks, NetworkData, Networks, Pid, Process, System};
 use thousands::Separable;
 use tokio::sync::Mutex;
 
-use crate::{enviorment, http_handler::AppState};
+use crate::http_handler::{password_manager, AppState};
 
 const KB: u64 = 1024;
 const MB: u64 = KB * 1024;
@@ -33,8 +34,7 @@ pub async fn get_info(
     State(app_state): State<Arc<AppState>>,
     Query(params): Query<QueryParams>,
 ) -> response::Html<String> {
-    let password = enviorment::get_enviorment("PASSWORD");
-    if params.password.is_none() || params.password.unwrap() != password {
+    if !password_manager::is_valid_password(&params.password) {
         return response::Html(String::from("<h1 align='center'>Bad password</h1>"));
     }
 
@@ -70,28 +70,107 @@ pub async fn get_info(
         ]);
         app_table.push([
             "Free OCs".to_owned(),
+            app_state
+                .oc_chache
+                .load()
+                .await
+                .len()
+                .separate_with_spaces(),
+        ]);
+        let sync_info = app_state.oc_chache.sync_info().await;
+        let format_sync = |date: Option<DateTime<Utc>>, rows: usize| match date {
+            Some(date) => format!(
+                "{} ({} rows)",
+                date.format("%Y.%m.%d. %H:%M:%S (UTC)"),
+                rows.separate_with_spaces()
+            ),
+            None => "-".to_owned(),
+        };
+        app_table.push([
+            "Free OC delta sync".to_owned(),
+            format_sync(sync_info.last_sync, sync_info.last_sync_rows),
+        ]);
+        app_table.push([
+            "Free OC full sync".to_owned(),
+            format_sync(sync_info.last_full_sync, sync_info.last_full_sync_rows),
+        ]);
+        app_table.push([
+            "Transfer encryption key".to_owned(),
+            app_state
+                .database
+                .tranfer_datas_table
+                .crypto
+                .active_key_id()
+                .unwrap_or("- (plaintext)")
+                .to_owned(),
+        ]);
+        let oc_cache = &app_state.database.oc_table.cache;
+        let (hits, misses) = oc_cache.stats();
+        app_table.push([
+            "Cached OCs".to_owned(),
+            oc_cache.len().await.separate_with_spaces(),
+        ]);
+        app_table.push([
+            "OC cache hits / misses".to_owned(),
             format!(
-                "{}",
-                app_state
-                    .oc_chache
-                    .read()
-                    .await
-                    .len()
-                    .separate_with_spaces()
+                "{} / {} ({:.1}%)",
+                hits.separate_with_spaces(),
+                misses.separate_with_spaces(),
+                hits as f64 * 100.0 / (hits + misses).max(1) as f64
             ),
         ]);
         app_table.push([
-            "Logs".to_owned(),
+            "Cached releases".to_owned(),
+            app_state
+                .latest_versions
+                .load()
+                .await
+                .map_or(0, |rows| rows.len())
+                .separate_with_spaces(),
+        ]);
+        let (verified, corrupt) = app_state.release_index.counts().await;
+        app_table.push([
+            "Release files (verified / corrupt)".to_owned(),
             format!(
-                "{}",
-                app_state
-                    .log_queue
-                    .lock()
-                    .await
-                    .len()
-                    .separate_with_spaces()
+                "{} / {}",
+                verified.separate_with_spaces(),
+                corrupt.separate_with_spaces()
             ),
         ]);
+        let releases = app_state.latest_versions.load().await.unwrap_or_default();
+        for release in releases.iter().filter(|release| release.rollout < 100) {
+            app_table.push([
+                format!("Rollout of {} (#{})", release.version, release.id),
+                format!("{}%", release.rollout),
+            ]);
+        }
+        for (version, count) in app_state.version_offers.all().await {
+            app_table.push([
+                format!("Offered version {version}"),
+                count.separate_with_spaces(),
+            ]);
+        }
+        app_table.push([
+            "Random histories".to_owned(),
+            app_state.random_selector.len().await.separate_with_spaces(),
+        ]);
+        app_table.push([
+            "Settings".to_owned(),
+            app_state.settings.len().await.separate_with_spaces(),
+        ]);
+        app_table.push([
+            "Ranked OCs".to_owned(),
+            app_state.oc_ranking.len().await.separate_with_spaces(),
+        ]);
+        app_table.push([
+            "Logs".to_owned(),
+            app_state
+                .log_queue
+                .lock()
+                .await
+                .len()
+                .separate_with_spaces(),
+        ]);
 
         let hmap_vec: Vec<&(Mutex<HashMap<String, Instant>>, Duration)> =
             app_state.rate_limit.iter().map(|a| a.1).collect();
@@ -99,10 +178,7 @@ pub async fn get_info(
         for (hmap, _) in hmap_vec {
             db += hmap.lock().await.len();
         }
-        app_table.push([
-            "Stored IPs".to_owned(),
-            format!("{}", db.separate_with_spaces()),
-        ]);
+        app_table.push(["Stored IPs".to_owned(), db.separate_with_spaces()]);
     }
     //---------------------------------------------------------
 
@@ -183,7 +259,7 @@ pub async fn get_info(
     // Network interfaces name, data received and data transmitted:
     let networks = Networks::new_with_refreshed_list();
     let mut networks: Vec<(&String, &NetworkData)> = networks.into_iter().collect();
-    networks.sort_by(|a, b| b.1.total_transmitted().cmp(&a.1.total_transmitted()));
+    networks.sort_by_key(|a| Reverse(a.1.total_transmitted()));
 
     for (interface_name, data) in networks {
         network_table.push([
@@ -218,7 +294,7 @@ pub async fn get_info(
     //---------------------------------------------------------
 
     let mut processes: Vec<(&Pid, &Process)> = sys.processes().iter().collect();
-    processes.sort_by(|a, b| b.1.memory().cmp(&a.1.memory()));
+    processes.sort_by_key(|a| Reverse(a.1.memory()));
 
     for (pid, process) in processes {
         processes_table.push([
@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::sync::{Arc, LazyLock};
 
 use axum::{
     extract::State,
@@ -6,11 +6,16 @@ use axum::{
     response::{IntoResponse, Response},
     Form,
 };
+use chrono::Utc;
+use inline_colorization::*;
 use serde::Deserialize;
 
 use crate::{
-    gachaplus_database::tranfer_datas_table::TransferDatas,
+    gachaplus_database::tranfer_datas_table::{TransferDatas, TransferDatasRow, TransferWrite},
     http_handler::{response_manager::ResponseManager, AppState},
+    save_data::SLOTS,
+    transfer_expiry::TransferExpiry,
+    transfer_owner, transfer_pin,
 };
 
 #[derive(Deserialize)]
@@ -18,7 +23,37 @@ use crate::{
 pub struct GetTransferInput {
     pub systemCall: String,
     pub accountx: u32,
+    pub pin: Option<String>,
 }
+#[derive(Deserialize)]
+pub struct AddTransferInput {
+    #[serde(flatten)]
+    pub datas: TransferDatas,
+    pub pin: Option<String>,
+    /// Owner token of the allocated `accountx`
+    pub token: Option<String>,
+}
+
+/// Snapshots kept per transfer *(setting `transfer_snapshot_count`)*
+pub const DEFAULT_SNAPSHOTS: u32 = 5;
+/// Tries to find a free `accountx`
+const ALLOCATE_ATTEMPTS: usize = 10;
+
+const EXPIRED_MSG: &str = "This transfer code has expired. Please create a new one.";
+const WRONG_PIN_MSG: &str = "Wrong PIN.";
+const LOCKED_MSG: &str = "Too many wrong PINs. Please try again later.";
+const CORRUPT_MSG: &str = "This transfer data is damaged and can't be loaded.";
+const NOT_OWNER_MSG: &str = "This account number belongs to another player.";
+const CHANGED_MSG: &str = "This transfer was changed meanwhile. Please try again.";
+const NOT_FOUND_MSG: &str = "There is no transfer with this account number.";
+
+/// `datastring<slot>` names in the order of the response
+static DATASTRING_NAMES: LazyLock<Vec<(String, u8)>> = LazyLock::new(|| {
+    SLOTS
+        .iter()
+        .map(|(slot, _)| (format!("datastring{slot}"), *slot))
+        .collect()
+});
 
 #[axum::debug_handler]
 pub async fn get_transfer_datas(
@@ -31,87 +66,181 @@ pub async fn get_transfer_datas(
     if input.accountx < 100_000_000 || input.accountx > 999_999_999 {
         return (StatusCode::BAD_REQUEST, "Invalid value of `accountx`").into_response();
     }
-    let res = app_state
-        .database
-        .tranfer_datas_table
-        .get(input.accountx)
-        .await;
-    if let Ok(row) = res {
-        if let Some(error) = row.data.is_invalid() {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Transfer data is not valid: {error}"),
-            )
-                .into_response();
+    let row = match load_row(&app_state, input.accountx).await {
+        Ok(row) => row,
+        Err(response) => return response,
+    };
+    check_login(&app_state, input, row).await
+}
+
+/// Loading the transfer, a corrupt row is a legacy error.
+async fn load_row(app_state: &AppState, accountx: u32) -> Result<TransferDatasRow, Response> {
+    match app_state.database.tranfer_datas_table.get(accountx).await {
+        Ok(row) => Ok(row),
+        // a corrupt row, the player gets a message instead of nothing
+        Err(sqlx::Error::Decode(error)) => {
+            println!(
+                "{color_red}{}\tTransfer: 🔥 Failed to decode the transfer of '{}': {error} 🔥{color_white}",
+                Utc::now().format("[%H:%M:%S]"),
+                accountx,
+            );
+            Err(legacy_error(CORRUPT_MSG))
         }
+        Err(error) => Err((
+            StatusCode::BAD_REQUEST,
+            format!("Database error: {:?}", error),
+        )
+            .into_response()),
+    }
+}
 
-        if app_state
-            .database
-            .tranfer_datas_table
-            .update(input.accountx)
-            .await
-            .is_err()
-        {
+async fn check_login(
+    app_state: &AppState,
+    input: GetTransferInput,
+    row: TransferDatasRow,
+) -> Response {
+    if let Some(hash) = row.pin.clone() {
+        if let Err(msg) = check_pin(app_state, input.accountx, input.pin, hash).await {
+            return legacy_error(msg);
+        }
+    }
+    if let Some(error) = row.data.is_invalid() {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Transfer data is not valid: {error}"),
+        )
+            .into_response();
+    }
+
+    let expiry = TransferExpiry::from_settings(&app_state.settings).await;
+    if expiry.is_expired(row.used, row.regdate, Utc::now()) {
+        return legacy_error(EXPIRED_MSG);
+    }
+    match app_state
+        .database
+        .tranfer_datas_table
+        .update(input.accountx, expiry.max_uses.unwrap_or(0))
+        .await
+    {
+        Ok(true) => (),
+        // used up by a parallel load
+        Ok(false) => return legacy_error(EXPIRED_MSG),
+        Err(_) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "Error at updating the database",
             )
-                .into_response();
+                .into_response()
         }
+    }
 
-        ResponseManager::new_ok()
-            .add("accountx", &row.accountx.to_string())
-            .add("datastring1", &row.data.datastring1)
-            .add("datastring2", &row.data.datastring2)
-            .add("datastring3", &row.data.datastring3)
-            .add("datastring4", &row.data.datastring4)
-            .add("datastring5", &row.data.datastring5)
-            .add("datastring6", &row.data.datastring6)
-            .add("datastring7", &row.data.datastring7)
-            .add("datastring8", &row.data.datastring8)
-            .add("datastring9", &row.data.datastring9)
-            .add("datastring10", &row.data.datastring10)
-            .add("datastring11", &row.data.datastring11)
-            .add("datastring12", &row.data.datastring12)
-            .add("datastring13", &row.data.datastring13)
-            .add("datastring14", &row.data.datastring14)
-            .add("datastring15", &row.data.datastring15)
-            .add("datastring16", &row.data.datastring16)
-            .add("datastring17", &row.data.datastring17)
-            .add("datastring18", &row.data.datastring18)
-            .add("datastring19", &row.data.datastring19)
-            .add("datastring20", &row.data.datastring20)
-            .into_response()
+    let accountx = row.accountx.to_string();
+    login_response(&accountx, &row.data).into_response()
+}
+
+/// `checkLogin`: the `accountx` and every datastring.
+fn login_response<'f>(accountx: &'f str, datas: &'f TransferDatas) -> ResponseManager<'f> {
+    let mut response = ResponseManager::new_ok().add("accountx", accountx);
+    for (name, slot) in DATASTRING_NAMES.iter() {
+        response = response.add(name, datas.datastring(*slot));
+    }
+    response
+}
+
+/// Legacy error with a message for the client *(sent with `200`, so `answer_200` keeps the message)*.
+fn legacy_error(msg: &str) -> Response {
+    legacy_error_response(msg).into_response()
+}
+fn legacy_error_response(msg: &str) -> ResponseManager<'_> {
+    ResponseManager::new_error().add("msg", msg)
+}
+
+/// Checking the PIN of a protected transfer, with the failed attempts limited per `accountx`.
+async fn check_pin(
+    app_state: &AppState,
+    accountx: u32,
+    pin: Option<String>,
+    hash: String,
+) -> Result<(), &'static str> {
+    // counted before the verification, so the parallel guesses are limited too
+    if !app_state.pin_attempts.reserve(accountx).await {
+        return Err(LOCKED_MSG);
+    }
+    let pin = pin.unwrap_or_default();
+    // hashing is slow on purpose, so it doesn't block the other requests
+    let is_valid = tokio::task::spawn_blocking(move || transfer_pin::verify_pin(pin.trim(), &hash))
+        .await
+        .unwrap_or(false);
+    if is_valid {
+        app_state.pin_attempts.reset(accountx).await;
+        Ok(())
     } else {
-        (
-            StatusCode::BAD_REQUEST,
-            format!("Database error: {:?}", res.unwrap_err()),
-        )
-            .into_response()
+        Err(WRONG_PIN_MSG)
     }
 }
 
 #[axum::debug_handler]
 pub async fn add_transfer_datas(
     State(app_state): State<Arc<AppState>>,
-    Form(input): Form<TransferDatas>,
+    Form(input): Form<AddTransferInput>,
 ) -> Response {
-    if let Some(error) = input.is_invalid() {
+    if let Some(error) = input.datas.is_invalid() {
         return (
             StatusCode::INTERNAL_SERVER_ERROR,
             format!("Input data is invalid {error}"),
         )
             .into_response();
     }
+    let pin = non_empty(input.pin);
+    if pin
+        .as_deref()
+        .is_some_and(|pin| !transfer_pin::is_valid_pin(pin))
+    {
+        return (StatusCode::BAD_REQUEST, "Invalid `pin`").into_response();
+    }
+
+    let accountx = input.datas.accountx.parse::<u32>().unwrap_or_default();
+    let token = non_empty(input.token);
+    let pin_checked = match check_write_access(&app_state, accountx, pin.clone(), &token).await {
+        Ok(pin_checked) => pin_checked,
+        Err(response) => return response,
+    };
+    let pin_hash = match pin {
+        Some(pin) => {
+            match tokio::task::spawn_blocking(move || transfer_pin::hash_pin(&pin)).await {
+                Ok(Ok(hash)) => Some(hash),
+                _ => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "Error at hashing the PIN",
+                    )
+                        .into_response()
+                }
+            }
+        }
+        None => None,
+    };
+
+    let snapshots = app_state
+        .settings
+        .get("transfer_snapshot_count", DEFAULT_SNAPSHOTS)
+        .await;
     match app_state
         .database
         .tranfer_datas_table
-        .insert_or_update(input)
+        .insert_or_update(
+            input.datas,
+            pin_hash,
+            token.as_deref(),
+            pin_checked.as_deref(),
+            snapshots,
+        )
         .await
     {
-        Ok(_) => ResponseManager::new_ok()
+        Ok(TransferWrite::Written) => ResponseManager::new_ok()
             .add("msg", "Uploaded successfully")
             .into_response(),
+        Ok(denied) => write_denied(denied),
         Err(error) => (
             StatusCode::INTERNAL_SERVER_ERROR,
             format!("Upload error: {error}"),
@@ -119,3 +248,196 @@ pub async fn add_transfer_datas(
             .into_response(),
     }
 }
+
+/// Legacy error of a write refused in its transaction.
+fn write_denied(denied: TransferWrite) -> Response {
+    match denied {
+        TransferWrite::NotOwner => legacy_error(NOT_OWNER_MSG),
+        TransferWrite::NotFound => legacy_error(NOT_FOUND_MSG),
+        _ => legacy_error(CHANGED_MSG),
+    }
+}
+
+/// Replacing only the uploaded datastrings of an existing transfer *(the PIN and the owner stay)*.
+#[axum::debug_handler]
+pub async fn update_transfer_datas(
+    State(app_state): State<Arc<AppState>>,
+    Form(input): Form<AddTransferInput>,
+) -> Response {
+    if input.datas.datastrings.is_empty() {
+        return (StatusCode::BAD_REQUEST, "No `datastring` to update").into_response();
+    }
+    if let Some(error) = input.datas.is_invalid_given() {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Input data is invalid {error}"),
+        )
+            .into_response();
+    }
+    let accountx = input.datas.accountx.parse::<u32>().unwrap_or_default();
+    let token = non_empty(input.token);
+    let pin_checked =
+        match check_write_access(&app_state, accountx, non_empty(input.pin), &token).await {
+            Ok(pin_checked) => pin_checked,
+            Err(response) => return response,
+        };
+
+    let snapshots = app_state
+        .settings
+        .get("transfer_snapshot_count", DEFAULT_SNAPSHOTS)
+        .await;
+    match app_state
+        .database
+        .tranfer_datas_table
+        .merge_update(
+            input.datas,
+            token.as_deref(),
+            pin_checked.as_deref(),
+            snapshots,
+        )
+        .await
+    {
+        Ok(TransferWrite::Written) => ResponseManager::new_ok()
+            .add("msg", "Updated successfully")
+            .into_response(),
+        Ok(denied) => write_denied(denied),
+        Err(error) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Update error: {error}"),
+        )
+            .into_response(),
+    }
+}
+
+/// Trimmed form value, `None` if it's empty.
+fn non_empty(value: Option<String>) -> Option<String> {
+    value
+        .map(|value| value.trim().to_owned())
+        .filter(|value| !value.is_empty())
+}
+
+/// Checking the owner token and the PIN before writing the transfer, returning the checked PIN hash.
+///
+/// *(The write checks them again in its transaction, the PIN is only verified here since it's slow.)*
+async fn check_write_access(
+    app_state: &AppState,
+    accountx: u32,
+    pin: Option<String>,
+    token: &Option<String>,
+) -> Result<Option<String>, Response> {
+    let table = &app_state.database.tranfer_datas_table;
+    let (reservation, protection) = tokio::try_join!(
+        table.get_reservation(accountx),
+        table.get_protection(accountx)
+    )
+    .map_err(|error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Upload error: {error}"),
+        )
+            .into_response()
+    })?;
+    // a reserved or owned number is only for its owner
+    let owner = protection
+        .as_ref()
+        .and_then(|protection| protection.owner_token.as_deref());
+    if !transfer_owner::is_owner(reservation.as_deref(), owner, token.as_deref()) {
+        return Err(legacy_error(NOT_OWNER_MSG));
+    }
+    // overwriting a protected transfer needs its PIN
+    if let Some(hash) = protection.and_then(|protection| protection.pin) {
+        check_pin(app_state, accountx, pin, hash.clone())
+            .await
+            .map_err(legacy_error)?;
+        return Ok(Some(hash));
+    }
+    Ok(None)
+}
+
+/// Allocating an unused `accountx` for a new player, reserved with an owner token.
+#[axum::debug_handler]
+pub async fn allocate_accountx(State(app_state): State<Arc<AppState>>) -> Response {
+    let table = &app_state.database.tranfer_datas_table;
+    let token = transfer_owner::generate_token();
+    let token_hash = transfer_owner::hash_token(&token);
+    for _ in 0..ALLOCATE_ATTEMPTS {
+        let accountx = transfer_owner::random_accountx();
+        match table.reserve(accountx, &token_hash).await {
+            Ok(true) => {
+                let accountx = accountx.to_string();
+                return ResponseManager::new_ok()
+                    .add("accountx", &accountx)
+                    .add("token", &token)
+                    .into_response();
+            }
+            // taken, trying another one
+            Ok(false) => (),
+            Err(error) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Database error: {error}"),
+                )
+                    .into_response()
+            }
+        }
+    }
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        "Failed to find a free `accountx`",
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn login_response_test() {
+        let mut datas = TransferDatas {
+            accountx: "123456789".to_owned(),
+            ..Default::default()
+        };
+        datas.datastrings.insert(1, "1|2".to_owned());
+        datas.datastrings.insert(20, "a b".to_owned());
+        let body = login_response("123456789", &datas).to_form_encoded();
+        let empty_slots: String = (2..=19).map(|slot| format!("&datastring{slot}=")).collect();
+        assert_eq!(
+            body,
+            format!(
+                "systemResult=2&accountx=123456789&datastring1=1%7C2{empty_slots}&datastring20=a+b"
+            )
+        );
+    }
+
+    #[test]
+    fn legacy_error_response_test() {
+        assert_eq!(
+            legacy_error_response(WRONG_PIN_MSG).to_form_encoded(),
+            "systemResult=3&msg=Wrong+PIN."
+        );
+    }
+
+    #[tokio::test]
+    async fn update_form_test() {
+        use axum::{body::Body, extract::FromRequest, http::Request};
+
+        let request = Request::post("/GPscripts/club_update.php")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(Body::from(
+                "accountx=123456789&datastring1=3%7C4&datastring3=&pin=1234",
+            ))
+            .unwrap();
+        let Form(input) = Form::<AddTransferInput>::from_request(request, &())
+            .await
+            .unwrap();
+        // only the given slots are replaced, the empty one is cleared
+        assert_eq!(input.datas.datastrings.len(), 2);
+        assert_eq!(input.datas.datastring(1), "3|4");
+        assert_eq!(
+            input.datas.datastrings.get(&3).map(|s| s.as_str()),
+            Some("")
+        );
+        assert_eq!(input.pin.as_deref(), Some("1234"));
+    }
+}
@@ -0,0 +1,98 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Form, Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    gachaplus_database::tranfer_datas_table::TransferDatas,
+    http_handler::{handlers::transfer_datas::DEFAULT_SNAPSHOTS, password_manager, AppState},
+};
+
+use super::database_error;
+
+#[derive(Deserialize)]
+pub struct SnapshotsParam {
+    password: Option<String>,
+    accountx: u32,
+}
+#[derive(Deserialize)]
+pub struct RestoreParam {
+    password: Option<String>,
+    accountx: u32,
+    id: u64,
+}
+
+#[derive(Serialize)]
+struct SnapshotInfo {
+    id: u64,
+    regdate: DateTime<Utc>,
+    key_id: Option<String>,
+    datas: Option<TransferDatas>,
+    /// Why the snapshot can't be decoded
+    error: Option<String>,
+}
+
+/// Listing the snapshots of a transfer, the newest first.
+#[axum::debug_handler]
+pub async fn get_snapshots(
+    State(app_state): State<Arc<AppState>>,
+    Query(param): Query<SnapshotsParam>,
+) -> Response {
+    if !password_manager::is_valid_password(&param.password) {
+        return (StatusCode::UNAUTHORIZED, "Bad password").into_response();
+    }
+    let table = &app_state.database.tranfer_datas_table;
+    match table.get_snapshots(param.accountx).await {
+        Ok(snapshots) => {
+            let infos: Vec<SnapshotInfo> = snapshots
+                .into_iter()
+                .map(|snapshot| {
+                    let (datas, error) = match table.decode_snapshot(&snapshot) {
+                        Ok(datas) => (Some(datas), None),
+                        Err(error) => (None, Some(error)),
+                    };
+                    SnapshotInfo {
+                        id: snapshot.id,
+                        regdate: snapshot.regdate,
+                        key_id: snapshot.key_id,
+                        datas,
+                        error,
+                    }
+                })
+                .collect();
+            Json(infos).into_response()
+        }
+        Err(error) => database_error(error),
+    }
+}
+
+/// Restoring a snapshot of a transfer, the replaced data becomes a snapshot.
+#[axum::debug_handler]
+pub async fn restore_snapshot(
+    State(app_state): State<Arc<AppState>>,
+    Form(param): Form<RestoreParam>,
+) -> Response {
+    if !password_manager::is_valid_password(&param.password) {
+        return (StatusCode::UNAUTHORIZED, "Bad password").into_response();
+    }
+    let snapshots = app_state
+        .settings
+        .get("transfer_snapshot_count", DEFAULT_SNAPSHOTS)
+        .await;
+    match app_state
+        .database
+        .tranfer_datas_table
+        .restore_snapshot(param.accountx, param.id, snapshots)
+        .await
+    {
+        Ok(true) => (StatusCode::OK, "Restored").into_response(),
+        Ok(false) => (StatusCode::BAD_REQUEST, "No result").into_response(),
+        Err(error) => database_error(error),
+    }
+}
@@ -1,18 +1,52 @@
-use std::sync::Arc;
+use std::{net::SocketAddr, sync::Arc};
 
-use axum::extract::State;
+use axum::{
+    extract::{ConnectInfo, Query, State},
+    http::HeaderMap,
+};
+use serde::Deserialize;
 
-use crate::http_handler::AppState;
+use crate::{
+    background_jobs::latest_version_cache::get_latest_versions,
+    http_handler::{ip_manager, AppState},
+    latest_version::ReleaseTarget,
+};
 
+#[derive(Deserialize)]
+pub struct VersionParam {
+    platform: Option<String>,
+    xbits: Option<u8>,
+    channel: Option<String>,
+}
+
+/// The release for the platform, architecture and channel of the client as `version|checksum|url`.
+///
+/// *(A new release only goes to the `rollout` percent of the IPs, the others get the previous version.)*
 #[axum::debug_handler]
-pub async fn verion_and_checksum(State(app_state): State<Arc<AppState>>) -> String {
-    let res = app_state
-        .database
-        .latestversion_table
-        .get_latest_version()
-        .await;
-    match res {
-        Ok(latest) => format!("{}|{}|{}", latest.version, latest.checksum, latest.url),
-        Err(_) => format!("0.0.0|error|error"),
+pub async fn verion_and_checksum(
+    State(app_state): State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Query(param): Query<VersionParam>,
+) -> String {
+    let target = ReleaseTarget::new(
+        param.platform.as_deref(),
+        param.xbits,
+        param.channel.as_deref(),
+        headers
+            .get("User-Agent")
+            .and_then(|user_agent| user_agent.to_str().ok()),
+    );
+    let bucket = ip_manager::ip_to_long(&ip_manager::get_user_ip(addr, headers));
+    let rows = get_latest_versions(&app_state).await;
+    match rows
+        .as_deref()
+        .and_then(|rows| target.select_rollout(rows, bucket))
+    {
+        Some(latest) => {
+            app_state.version_offers.count(&latest.version).await;
+            format!("{}|{}|{}", latest.version, latest.checksum, latest.url)
+        }
+        None => "0.0.0|error|error".to_owned(),
     }
 }
@@ -15,7 +15,7 @@ pub async fn answer_200(
 ) -> Response {
     let response = next.run(request).await;
     if response.status() == StatusCode::OK || response.status() == StatusCode::PERMANENT_REDIRECT {
-        return response;
+        response
     } else {
         if !path.to_string().starts_with("/GPscripts/") {
             return response;
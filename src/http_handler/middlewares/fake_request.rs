@@ -1,24 +1,28 @@
 use std::sync::Arc;
 
-#[cfg(not(debug_assertions))]
-use axum::{
-    extract::OriginalUri,
-    http::{HeaderMap, StatusCode, Uri},
-    response::IntoResponse,
-};
 use axum::{
-    extract::{Request, State},
+    extract::{OriginalUri, Request, State},
+    http::{HeaderMap, Uri},
     middleware::Next,
     response::Response,
 };
+#[cfg(not(debug_assertions))]
+use axum::{http::StatusCode, response::IntoResponse};
+
+use crate::{
+    background_jobs::latest_version_cache::get_latest_versions,
+    client_version::{self, ClientVersion, VERSION_HEADER},
+    http_handler::{response_manager::ResponseManager, AppState},
+    latest_version::ReleaseTarget,
+};
 
-use crate::http_handler::AppState;
+const UPDATE_MSG: &str = "This version of the game is too old. Please update it!";
 
 pub async fn fake_request_middleware(
-    #[allow(unused_variables)] State(app_state): State<Arc<AppState>>,
+    State(app_state): State<Arc<AppState>>,
 
-    #[cfg(not(debug_assertions))] headers: HeaderMap,
-    #[cfg(not(debug_assertions))] OriginalUri(path): OriginalUri,
+    headers: HeaderMap,
+    OriginalUri(path): OriginalUri,
 
     request: Request,
     next: Next,
@@ -27,21 +31,63 @@ pub async fn fake_request_middleware(
     if app_state.request_protection {
         if let Some(error) = is_fake(&headers, &path) {
             //println!("{:?}", headers);
-            (StatusCode::BAD_REQUEST, format!("FAKE REQUEST: {error}")).into_response()
-        } else {
-            next.run(request).await
+            return (StatusCode::BAD_REQUEST, format!("FAKE REQUEST: {error}")).into_response();
         }
-    } else {
-        next.run(request).await
     }
-    #[cfg(debug_assertions)]
+    if let Some(response) = check_client_version(&app_state, &headers, &path).await {
+        return response;
+    }
     next.run(request).await
 }
 
+/// Legacy "please update" error, if the client is older than the minimum of the route.
+///
+/// *(Only the clients sending their version in the header, `startup.php` checks the version of its form.)*
+async fn check_client_version(
+    app_state: &AppState,
+    headers: &HeaderMap,
+    path: &Uri,
+) -> Option<Response> {
+    let path = path.path();
+    if !path.starts_with("/GPscripts/")
+        || path == "/GPscripts/startup.php"
+        || path == "/GPscripts/latestversion_and_checksum.php"
+    {
+        return None;
+    }
+    let version = headers
+        .get(VERSION_HEADER)
+        .and_then(|version| version.to_str().ok())
+        .and_then(|version| version.parse::<ClientVersion>().ok())?;
+    let min_version = client_version::min_version(&app_state.settings, path).await?;
+    if version >= min_version {
+        return None;
+    }
+    let user_agent = headers
+        .get("User-Agent")
+        .and_then(|user_agent| user_agent.to_str().ok());
+    let target = ReleaseTarget::new(None, None, None, user_agent);
+    Some(update_response(app_state, &target).await)
+}
+
+/// The "please update" error with the download URL of the newest release for the `target`.
+pub async fn update_response(app_state: &AppState, target: &ReleaseTarget) -> Response {
+    let versions = get_latest_versions(app_state).await;
+    let url = versions
+        .as_deref()
+        .and_then(|rows| target.select(rows))
+        .map(|latest| latest.url.as_str())
+        .unwrap_or_default();
+    ResponseManager::new_error()
+        .add("msg", UPDATE_MSG)
+        .add("url", url)
+        .into_response()
+}
+
 #[cfg(not(debug_assertions))]
 fn is_fake(headers: &HeaderMap, path: &Uri) -> Option<&'static str> {
     {
-        if path.to_string() == "/GPscripts/latestversion_and_checksum.php" {
+        if path == "/GPscripts/latestversion_and_checksum.php" {
             return None;
         }
         if !path.to_string().starts_with("/GPscripts") {
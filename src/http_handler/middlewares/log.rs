@@ -2,9 +2,9 @@ use std::{net::SocketAddr, time::Instant};
 
 use axum::http::HeaderMap;
 use axum::{
-    body::{Body, Bytes},
+    body::{Body, Bytes, HttpBody},
     extract::{ConnectInfo, OriginalUri, Request},
-    http::StatusCode,
+    http::{header, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
 };
@@ -46,6 +46,32 @@ pub async fn log(
             path,
             color_white,
         );
+    } else if !is_text_body(&response) {
+        // the downloads are streamed through, only their length is logged
+        let len = response
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|len| len.to_str().ok())
+            .map(|len| len.to_owned())
+            .or_else(|| {
+                response
+                    .body()
+                    .size_hint()
+                    .exact()
+                    .map(|len| len.to_string())
+            })
+            .unwrap_or(String::from("<streamed>"));
+        println!(
+            "{}{}\tCode: {}\tIP:{}\tDelay: {:.3} ms\tRequest: {}\tLength: {}{}",
+            color_yellow,
+            Utc::now().format("[%H:%M:%S]"),
+            response.status(),
+            ip,
+            delay_in_ms,
+            path,
+            len,
+            color_white,
+        );
     } else {
         // Buffer the response body
         let (parts, body) = response.into_parts();
@@ -76,6 +102,32 @@ pub async fn log(
     response
 }
 
+/// Whether the body is a complete text, like the error messages, so it can be buffered for the log.
+///
+/// *(Not the ranges, the `304`s and the binaries or the streamed bodies, which may never end.)*
+fn is_text_body(response: &Response) -> bool {
+    if matches!(
+        response.status(),
+        StatusCode::PARTIAL_CONTENT | StatusCode::NOT_MODIFIED | StatusCode::RANGE_NOT_SATISFIABLE
+    ) {
+        return false;
+    }
+    let is_text = match response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+    {
+        Some(content_type) => {
+            let content_type = content_type.trim().to_ascii_lowercase();
+            content_type.starts_with("text/")
+                || content_type.starts_with("application/json")
+                || content_type.starts_with("application/x-www-form-urlencoded")
+        }
+        None => true,
+    };
+    is_text && response.body().size_hint().exact().is_some()
+}
+
 pub async fn get_body<B>(body: B) -> (Option<Bytes>, Option<String>)
 where
     B: axum::body::HttpBody<Data = Bytes>,
@@ -94,3 +146,58 @@ where
         (None, None)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use axum::{middleware, routing, Router};
+    use tokio_util::io::ReaderStream;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn request(path: &str) -> Request {
+        let mut request = Request::builder().uri(path).body(Body::empty()).unwrap();
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 8080))));
+        request
+    }
+
+    #[tokio::test]
+    async fn log_streamed_body_test() {
+        let router = Router::new()
+            .route(
+                "/range",
+                routing::get(|| async {
+                    // a range of a body that never ends, it can't be collected
+                    Response::builder()
+                        .status(StatusCode::PARTIAL_CONTENT)
+                        .header(header::CONTENT_TYPE, "application/octet-stream")
+                        .body(Body::from_stream(ReaderStream::new(tokio::io::repeat(0))))
+                        .unwrap()
+                }),
+            )
+            .route(
+                "/error",
+                routing::get(|| async { (StatusCode::BAD_REQUEST, "Invalid value") }),
+            )
+            .layer(middleware::from_fn(log));
+
+        let response = tokio::time::timeout(
+            Duration::from_secs(5),
+            router.clone().oneshot(request("/range")),
+        )
+        .await
+        .expect("the ranged download was collected")
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert!(response.body().size_hint().exact().is_none());
+
+        let response = router.oneshot(request("/error")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let (_, body) = get_body(response.into_body()).await;
+        assert_eq!(body.as_deref(), Some("Invalid value"));
+    }
+}
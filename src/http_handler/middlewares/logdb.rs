@@ -30,10 +30,12 @@ pub async fn log_db(
             "/GPscripts/latestversion_and_checksum.php" => Some(ActionEnum::WINAPPgetversion),
             "/GPscripts/startup.php" => Some(ActionEnum::Startups),
             "/GPscripts/randomcode.php" => Some(ActionEnum::OCrandom),
+            "/GPscripts/dailycode.php" => Some(ActionEnum::OCdaily),
             "/GPscripts/club_import.php" => Some(ActionEnum::OCimport),
             "/GPscripts/club_export.php" => Some(ActionEnum::OCexport),
             "/GPscripts/club_login.php" => Some(ActionEnum::ALLimport),
             "/GPscripts/club_register.php" => Some(ActionEnum::ALLexport),
+            "/GPscripts/club_update.php" => Some(ActionEnum::ALLexport),
             _ => None,
         };
         if let Some(code) = code {
@@ -55,15 +55,25 @@ fn create_rules() -> HashMap<&'static str, Duration> {
         rules.insert("/GPscripts/club_import.php", Duration::from_secs(1));
         rules.insert("/GPscripts/club_register.php", Duration::from_secs(60));
         rules.insert("/GPscripts/club_login.php", Duration::from_secs(10));
+        rules.insert("/GPscripts/club_allocate.php", Duration::from_secs(60));
+        rules.insert("/GPscripts/club_update.php", Duration::from_secs(10));
         rules.insert("/GPscripts/startup.php", Duration::from_secs(15));
         rules.insert("/GPscripts/randomcode.php", Duration::from_millis(200));
+        rules.insert("/GPscripts/club_ranking_optout.php", Duration::from_secs(2));
+        rules.insert("/GPscripts/club_report.php", Duration::from_secs(10));
+        rules.insert("/ranking", Duration::from_millis(500));
+        rules.insert("/random_ocs", Duration::from_millis(500));
+        rules.insert("/GPscripts/dailycode.php", Duration::from_millis(500));
+        rules.insert("/oc_of_the_day", Duration::from_millis(500));
         rules
     }
     #[cfg(debug_assertions)]
     HashMap::new()
 }
 
-pub fn create_ratelimit() -> HashMap<&'static str, (Mutex<HashMap<String, Instant>>, Duration)> {
+pub type RateLimitCache = HashMap<&'static str, (Mutex<HashMap<String, Instant>>, Duration)>;
+
+pub fn create_ratelimit() -> RateLimitCache {
     let rules = create_rules();
     let mut cache = HashMap::new();
     for (path, dur) in rules {
@@ -0,0 +1,6 @@
+use crate::enviorment;
+
+/// Checking the admin `password` *(query parameter)* against the `PASSWORD` enviorment.
+pub fn is_valid_password(password: &Option<String>) -> bool {
+    password.as_deref() == Some(enviorment::get_enviorment("PASSWORD").as_str())
+}
@@ -15,9 +15,9 @@ impl<'f> ResponseManager<'f> {
             params: vec![("systemResult", "3")],
         }
     }
-    pub fn to_form_encoded(self) -> String {
+    pub fn to_form_encoded(&self) -> String {
         url::form_urlencoded::Serializer::new(String::new())
-            .extend_pairs(self.params)
+            .extend_pairs(self.params.iter())
             .finish()
     }
     pub fn into_response(self) -> Response {
@@ -0,0 +1,352 @@
+use std::{collections::HashMap, sync::Arc};
+
+use tokio::sync::{Mutex, RwLock};
+
+use crate::{
+    client_version::ClientVersion, gachaplus_database::latestversion_table::LatestVersionRow,
+};
+
+pub const STABLE_CHANNEL: &str = "stable";
+pub const BETA_CHANNEL: &str = "beta";
+
+/// Which release a client needs: the platform, the architecture and the channel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReleaseTarget {
+    pub platform: Option<String>,
+    pub xbits: Option<u8>,
+    pub channel: String,
+}
+
+impl ReleaseTarget {
+    /// From the request parameters, the platform falls back to the `User-Agent`.
+    ///
+    /// *(Without any, it's the newest stable release, like before the channels.)*
+    pub fn new(
+        platform: Option<&str>,
+        xbits: Option<u8>,
+        channel: Option<&str>,
+        user_agent: Option<&str>,
+    ) -> Self {
+        let platform = platform
+            .and_then(normalize_platform)
+            .or_else(|| user_agent.and_then(platform_from_user_agent));
+        let channel = match channel.map(|channel| channel.trim().to_ascii_lowercase()) {
+            Some(channel) if channel == BETA_CHANNEL => BETA_CHANNEL,
+            _ => STABLE_CHANNEL,
+        };
+        Self {
+            platform: platform.map(|platform| platform.to_owned()),
+            xbits: xbits.filter(|xbits| *xbits == 32 || *xbits == 64),
+            channel: channel.to_owned(),
+        }
+    }
+
+    fn accepts(&self, row: &LatestVersionRow) -> bool {
+        if row.rollbackdate.is_some() {
+            return false;
+        }
+        let channel = row.channel == self.channel
+            // the beta testers get the newer stable releases too
+            || (self.channel == BETA_CHANNEL && row.channel == STABLE_CHANNEL);
+        // a `None` row is for any client, an unknown client only gets those
+        let platform = match (&self.platform, &row.platform) {
+            (Some(platform), Some(row_platform)) => platform.eq_ignore_ascii_case(row_platform),
+            (_, None) => true,
+            (None, Some(_)) => false,
+        };
+        let xbits = match (self.xbits, row.xbits) {
+            (Some(xbits), Some(row_xbits)) => xbits == row_xbits,
+            (_, None) => true,
+            (None, Some(_)) => false,
+        };
+        channel && platform && xbits
+    }
+
+    /// The release for the target: the newest version, the most specific row of it.
+    pub fn select<'a>(&self, rows: &'a [LatestVersionRow]) -> Option<&'a LatestVersionRow> {
+        self.candidates(rows).into_iter().next()
+    }
+
+    /// The release with a staged rollout: the newest goes to the `bucket`s *(`0..100`)* under its `rollout`,
+    /// the others get the newest release of a lower version.
+    pub fn select_rollout<'a>(
+        &self,
+        rows: &'a [LatestVersionRow],
+        bucket: Option<u32>,
+    ) -> Option<&'a LatestVersionRow> {
+        let candidates = self.candidates(rows);
+        let newest = *candidates.first()?;
+        let in_rollout = bucket.is_some_and(|bucket| bucket % 100 < newest.rollout as u32);
+        if in_rollout || newest.rollout >= 100 {
+            return Some(newest);
+        }
+        let previous = candidates
+            .iter()
+            .find(|row| release_version(row) < release_version(newest));
+        Some(previous.copied().unwrap_or(newest))
+    }
+
+    /// The accepted releases, the best first: the newest version, then the most specific row.
+    ///
+    /// *(The versions which aren't `major.minor.patch` come last.)*
+    fn candidates<'a>(&self, rows: &'a [LatestVersionRow]) -> Vec<&'a LatestVersionRow> {
+        let mut candidates: Vec<&LatestVersionRow> =
+            rows.iter().filter(|row| self.accepts(row)).collect();
+        candidates.sort_by_key(|row| {
+            let specific = row.platform.is_some() as u8 + row.xbits.is_some() as u8;
+            std::cmp::Reverse((release_version(row), specific, row.id))
+        });
+        candidates
+    }
+}
+
+fn release_version(row: &LatestVersionRow) -> Option<ClientVersion> {
+    row.version.parse().ok()
+}
+
+pub fn normalize_platform(platform: &str) -> Option<&'static str> {
+    match platform.trim().to_ascii_lowercase().as_str() {
+        "windows" => Some("Windows"),
+        "android" => Some("Android"),
+        _ => None,
+    }
+}
+
+/// The `latestversion` rows in memory, so the version requests don't hit the database.
+///
+/// *(During a database outage the last loaded rows are served.)*
+#[derive(Default)]
+pub struct LatestVersionCache {
+    rows: RwLock<Option<Arc<Vec<LatestVersionRow>>>>,
+}
+
+impl LatestVersionCache {
+    /// The last loaded rows, `None` before the first load.
+    pub async fn load(&self) -> Option<Arc<Vec<LatestVersionRow>>> {
+        self.rows.read().await.clone()
+    }
+
+    pub async fn store(&self, rows: Vec<LatestVersionRow>) {
+        *self.rows.write().await = Some(Arc::new(rows));
+    }
+}
+
+/// How many times each version was offered since the start.
+#[derive(Default)]
+pub struct VersionOffers {
+    counts: Mutex<HashMap<String, u64>>,
+}
+
+impl VersionOffers {
+    pub async fn count(&self, version: &str) {
+        *self
+            .counts
+            .lock()
+            .await
+            .entry(version.to_owned())
+            .or_default() += 1;
+    }
+
+    /// The counts, the most offered first.
+    pub async fn all(&self) -> Vec<(String, u64)> {
+        let mut counts: Vec<(String, u64)> = self
+            .counts
+            .lock()
+            .await
+            .iter()
+            .map(|(version, count)| (version.clone(), *count))
+            .collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        counts
+    }
+}
+
+fn platform_from_user_agent(user_agent: &str) -> Option<&'static str> {
+    if user_agent.contains("Android") {
+        Some("Android")
+    } else if user_agent.contains("Windows") {
+        Some("Windows")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    fn row(id: u32, platform: Option<&str>, xbits: Option<u8>, channel: &str) -> LatestVersionRow {
+        LatestVersionRow {
+            id,
+            version: format!("1.{id}.0"),
+            checksum: String::new(),
+            url: String::new(),
+            platform: platform.map(|platform| platform.to_owned()),
+            xbits,
+            channel: channel.to_owned(),
+            rollout: 100,
+            regdate: Utc::now(),
+            rollbackdate: None,
+        }
+    }
+
+    #[test]
+    fn release_rollback_test() {
+        let mut rows = vec![
+            row(1, None, None, STABLE_CHANNEL),
+            row(2, None, None, STABLE_CHANNEL),
+        ];
+        rows[1].rollbackdate = Some(Utc::now());
+        let target = ReleaseTarget::new(None, None, None, None);
+        assert_eq!(target.select(&rows).map(|row| row.id), Some(1));
+        assert_eq!(
+            target.select_rollout(&rows, Some(5)).map(|row| row.id),
+            Some(1)
+        );
+        rows[0].rollbackdate = Some(Utc::now());
+        assert!(target.select(&rows).is_none());
+    }
+
+    #[test]
+    fn release_rollout_test() {
+        let mut rows = vec![
+            row(1, None, None, STABLE_CHANNEL),
+            row(2, None, None, STABLE_CHANNEL),
+        ];
+        let target = ReleaseTarget::new(None, None, None, None);
+        let select = |rows: &[LatestVersionRow], bucket| {
+            target.select_rollout(rows, bucket).map(|row| row.id)
+        };
+        assert_eq!(select(&rows, Some(5)), Some(2));
+        rows[1].rollout = 10;
+        assert_eq!(select(&rows, Some(5)), Some(2));
+        assert_eq!(select(&rows, Some(105)), Some(2));
+        assert_eq!(select(&rows, Some(10)), Some(1));
+        assert_eq!(select(&rows, None), Some(1));
+        rows[1].rollout = 0;
+        assert_eq!(select(&rows, Some(5)), Some(1));
+        // nothing to fall back to
+        assert_eq!(select(&rows[1..], Some(99)), Some(2));
+    }
+
+    #[test]
+    fn release_rollout_previous_test() {
+        let mut rows = vec![
+            row(1, None, None, STABLE_CHANNEL),
+            row(2, Some("Windows"), None, STABLE_CHANNEL),
+            row(3, Some("Android"), None, STABLE_CHANNEL),
+            row(4, None, None, STABLE_CHANNEL),
+            row(5, Some("Windows"), Some(64), STABLE_CHANNEL),
+        ];
+        rows[4].version = rows[3].version.clone();
+        rows[3].rollout = 10;
+        rows[4].rollout = 10;
+        let select = |platform, xbits| {
+            ReleaseTarget::new(platform, xbits, None, None)
+                .select_rollout(&rows, Some(50))
+                .map(|row| row.id)
+        };
+        // the previous version of the same target, not the next row or an other platform's
+        assert_eq!(select(Some("Windows"), Some(64)), Some(2));
+        assert_eq!(select(Some("Android"), None), Some(3));
+        assert_eq!(select(None, None), Some(1));
+    }
+
+    #[tokio::test]
+    async fn latest_version_cache_test() {
+        let cache = LatestVersionCache::default();
+        assert!(cache.load().await.is_none());
+        cache.store(vec![row(1, None, None, STABLE_CHANNEL)]).await;
+        let rows = cache.load().await.unwrap();
+        cache.store(Vec::new()).await;
+        // the readers keep their snapshot
+        assert_eq!(rows.len(), 1);
+        assert!(cache.load().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn version_offers_test() {
+        let offers = VersionOffers::default();
+        offers.count("1.1.0").await;
+        offers.count("1.2.0").await;
+        offers.count("1.2.0").await;
+        assert_eq!(
+            offers.all().await,
+            vec![("1.2.0".to_owned(), 2), ("1.1.0".to_owned(), 1)]
+        );
+    }
+
+    #[test]
+    fn release_target_test() {
+        let target = ReleaseTarget::new(
+            None,
+            Some(64),
+            Some("BETA"),
+            Some("Mozilla/5.0 (Windows; U; en-US) AdobeAIR/33.0"),
+        );
+        assert_eq!(target.platform.as_deref(), Some("Windows"));
+        assert_eq!(target.channel, BETA_CHANNEL);
+
+        let target = ReleaseTarget::new(Some("android"), Some(16), Some("nightly"), None);
+        assert_eq!(target.platform.as_deref(), Some("Android"));
+        assert_eq!(target.xbits, None);
+        assert_eq!(target.channel, STABLE_CHANNEL);
+    }
+
+    #[test]
+    fn release_select_test() {
+        let rows = vec![
+            row(1, None, None, STABLE_CHANNEL),
+            row(2, Some("Windows"), Some(64), STABLE_CHANNEL),
+            row(3, Some("Windows"), Some(32), STABLE_CHANNEL),
+            row(4, Some("Windows"), Some(64), BETA_CHANNEL),
+            row(5, Some("Android"), None, STABLE_CHANNEL),
+        ];
+        let select = |platform, xbits, channel| {
+            ReleaseTarget::new(platform, xbits, channel, None)
+                .select(&rows)
+                .map(|row| row.id)
+        };
+        // like before the channels: the newest stable for any client
+        assert_eq!(select(None, None, None), Some(1));
+        assert_eq!(select(Some("Windows"), Some(64), None), Some(2));
+        assert_eq!(select(Some("Windows"), Some(32), None), Some(3));
+        assert_eq!(select(Some("Windows"), Some(64), Some("beta")), Some(4));
+        assert_eq!(select(Some("Android"), Some(64), None), Some(5));
+        assert_eq!(select(Some("Android"), None, None), Some(5));
+        // the architecture is unknown, no 32 or 64 bit build
+        assert_eq!(select(Some("Windows"), None, None), Some(1));
+        // no release for the platform, the generic one
+        assert_eq!(
+            ReleaseTarget::new(None, None, None, Some("Mozilla/5.0 (Linux)"))
+                .select(&rows[..1])
+                .map(|row| row.id),
+            Some(1)
+        );
+        // only platform builds, nothing for an unknown platform
+        assert!(ReleaseTarget::new(None, None, None, None)
+            .select(&rows[1..4])
+            .is_none());
+    }
+
+    #[test]
+    fn release_select_newest_test() {
+        let mut rows = vec![
+            row(1, Some("Windows"), Some(64), STABLE_CHANNEL),
+            row(2, None, None, STABLE_CHANNEL),
+            row(3, Some("Windows"), None, STABLE_CHANNEL),
+        ];
+        rows[0].version = "1.0.0".to_owned();
+        rows[1].version = "1.1.0".to_owned();
+        rows[2].version = "1.1.0".to_owned();
+        let target = ReleaseTarget::new(Some("Windows"), Some(64), None, None);
+        // a newer generic release wins over an older specific one, the specific one of the same version first
+        assert_eq!(target.select(&rows).map(|row| row.id), Some(3));
+        assert_eq!(target.select(&rows[..2]).map(|row| row.id), Some(2));
+        // the order of the versions, not of the rows
+        rows[0].version = "1.10.0".to_owned();
+        assert_eq!(target.select(&rows).map(|row| row.id), Some(1));
+    }
+}
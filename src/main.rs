@@ -11,10 +11,24 @@ use std::net::{IpAddr, Ipv6Addr, SocketAddr};
 
 mod background_jobs;
 mod character_code;
+mod client_version;
 mod enviorment;
+mod free_oc_cache;
 mod gachaplus_database;
 mod http_handler;
+mod latest_version;
+mod oc_cache;
+mod oc_of_the_day;
+mod oc_ranking;
+mod random_selector;
+mod release_files;
+mod save_data;
+mod settings;
 mod tests;
+mod transfer_crypto;
+mod transfer_expiry;
+mod transfer_owner;
+mod transfer_pin;
 
 use http_handler::AppState;
 
@@ -0,0 +1,214 @@
+use std::{
+    num::NonZeroUsize,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use lru::LruCache;
+use tokio::sync::Mutex;
+
+use crate::{gachaplus_database::oc_table::Oc, settings::Settings};
+
+/// Default count of the cached OCs *(can be changed with the `oc_cache_size` setting, `0` turns it off)*
+pub const DEFAULT_CAPACITY: usize = 10_000;
+/// Default lifetime of a cached OC *(can be changed with the `oc_cache_ttl_seconds` setting)*
+pub const DEFAULT_TTL: Duration = Duration::from_secs(5 * 60);
+
+struct CachedOc {
+    oc: Oc,
+    loaded: Instant,
+}
+
+struct CacheState {
+    /// `None` if the cache is turned off
+    ocs: Option<LruCache<String, CachedOc>>,
+    ttl: Duration,
+    /// Increased by every invalidation, the fills started before it are dropped
+    generation: u64,
+}
+
+/// Bounded LRU cache of the regular OCs with a lifetime, in front of the `oc` table.
+pub struct OcCache {
+    state: Mutex<CacheState>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl Default for OcCache {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(CacheState {
+                ocs: NonZeroUsize::new(DEFAULT_CAPACITY).map(LruCache::new),
+                ttl: DEFAULT_TTL,
+                generation: 0,
+            }),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+}
+
+impl OcCache {
+    /// Applying a new size and lifetime, keeping the most recent entries that still fit.
+    pub async fn configure(&self, capacity: usize, ttl: Duration) {
+        let mut state = self.state.lock().await;
+        state.ttl = ttl;
+        match (NonZeroUsize::new(capacity), state.ocs.as_mut()) {
+            (Some(capacity), Some(ocs)) => ocs.resize(capacity),
+            (Some(capacity), None) => state.ocs = Some(LruCache::new(capacity)),
+            (None, _) => state.ocs = None,
+        }
+    }
+
+    pub async fn configure_from_settings(&self, settings: &Settings) {
+        let capacity = settings.get("oc_cache_size", DEFAULT_CAPACITY).await;
+        let ttl = settings
+            .get("oc_cache_ttl_seconds", DEFAULT_TTL.as_secs())
+            .await;
+        self.configure(capacity, Duration::from_secs(ttl)).await;
+    }
+
+    pub async fn get(&self, accountx: &str) -> Option<Oc> {
+        let oc = self.get_at(accountx, Instant::now()).await;
+        match oc {
+            Some(_) => self.hits.fetch_add(1, Ordering::Relaxed),
+            None => self.misses.fetch_add(1, Ordering::Relaxed),
+        };
+        oc
+    }
+
+    async fn get_at(&self, accountx: &str, now: Instant) -> Option<Oc> {
+        let mut state = self.state.lock().await;
+        let ttl = state.ttl;
+        let ocs = state.ocs.as_mut()?;
+        let cached = ocs.get(accountx)?;
+        if now.duration_since(cached.loaded) < ttl {
+            return Some(cached.oc.clone());
+        }
+        ocs.pop(accountx);
+        None
+    }
+
+    /// The generation to pass to `insert`, read before loading the OC.
+    pub async fn generation(&self) -> u64 {
+        self.state.lock().await.generation
+    }
+
+    /// Caching the loaded OC, unless an invalidation happened since its `generation` *(it may be stale)*.
+    pub async fn insert(&self, oc: Oc, generation: u64) {
+        let mut state = self.state.lock().await;
+        if state.generation != generation {
+            return;
+        }
+        if let Some(ocs) = state.ocs.as_mut() {
+            ocs.put(
+                oc.accountx.to_owned(),
+                CachedOc {
+                    oc,
+                    loaded: Instant::now(),
+                },
+            );
+        }
+    }
+
+    pub async fn invalidate(&self, accountx: &str) {
+        let mut state = self.state.lock().await;
+        state.generation += 1;
+        if let Some(ocs) = state.ocs.as_mut() {
+            ocs.pop(accountx);
+        }
+    }
+
+    pub async fn len(&self) -> usize {
+        self.state
+            .lock()
+            .await
+            .ocs
+            .as_ref()
+            .map_or(0, |ocs| ocs.len())
+    }
+
+    /// Count of the hits and the misses since the start.
+    pub fn stats(&self) -> (u64, u64) {
+        (
+            self.hits.load(Ordering::Relaxed),
+            self.misses.load(Ordering::Relaxed),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn oc_cache_lru_test() {
+        let cache = OcCache::default();
+        cache.configure(2, DEFAULT_TTL).await;
+        for accountx in ["AAAAAAA", "BBBBBBB"] {
+            cache
+                .insert(
+                    Oc::new(accountx.to_owned(), String::new(), String::new()),
+                    0,
+                )
+                .await;
+        }
+        // `AAAAAAA` becomes the most recently used, so `BBBBBBB` is evicted
+        assert!(cache.get("AAAAAAA").await.is_some());
+        cache
+            .insert(
+                Oc::new("CCCCCCC".to_owned(), String::new(), String::new()),
+                0,
+            )
+            .await;
+        assert!(cache.get("BBBBBBB").await.is_none());
+        assert_eq!(cache.len().await, 2);
+
+        cache.invalidate("AAAAAAA").await;
+        assert!(cache.get("AAAAAAA").await.is_none());
+        assert_eq!(cache.stats(), (1, 2));
+    }
+
+    #[tokio::test]
+    async fn oc_cache_stale_fill_test() {
+        let cache = OcCache::default();
+        let oc = |mycode: &str| Oc::new("AAAAAAA".to_owned(), String::new(), mycode.to_owned());
+        // a load starts, then the OC is updated before the load is cached
+        let generation = cache.generation().await;
+        cache.invalidate("AAAAAAA").await;
+        cache.insert(oc("stale"), generation).await;
+        assert!(cache.get("AAAAAAA").await.is_none());
+
+        // the next load is cached
+        cache.insert(oc("fresh"), cache.generation().await).await;
+        assert_eq!(cache.get("AAAAAAA").await.unwrap().mycode, "fresh");
+    }
+
+    #[tokio::test]
+    async fn oc_cache_ttl_test() {
+        let cache = OcCache::default();
+        cache.configure(10, Duration::from_secs(60)).await;
+        cache
+            .insert(
+                Oc::new("AAAAAAA".to_owned(), String::new(), String::new()),
+                0,
+            )
+            .await;
+        let now = Instant::now();
+        assert!(cache.get_at("AAAAAAA", now).await.is_some());
+        assert!(cache
+            .get_at("AAAAAAA", now + Duration::from_secs(61))
+            .await
+            .is_none());
+        assert_eq!(cache.len().await, 0);
+
+        cache.configure(0, DEFAULT_TTL).await;
+        cache
+            .insert(
+                Oc::new("AAAAAAA".to_owned(), String::new(), String::new()),
+                0,
+            )
+            .await;
+        assert!(cache.get("AAAAAAA").await.is_none());
+    }
+}
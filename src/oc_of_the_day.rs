@@ -0,0 +1,131 @@
+use chrono::NaiveDate;
+use md5::{Digest, Md5};
+
+/// Salt of the daily hash, so the order can't be guessed from the pool alone.
+const HASH_SALT: &str = "gachaplus-oc-of-the-day";
+
+/// The OC of the `day` from the `pool` *(`accountx`s)* with its cycle.
+///
+/// `featured` is the OCs featured in the current `cycle`, they are skipped until every OC of the pool
+/// was featured, then the next cycle starts. *(The cycles are stored with the picks, so it holds even if
+/// the pool changes.)* The OC with the lowest MD5 of the day and its `accountx` wins, which doesn't depend
+/// on the order of the pool or on the `rand` version.
+pub fn daily_pick<'a>(
+    day: NaiveDate,
+    pool: &[&'a str],
+    cycle: u32,
+    featured: &[String],
+) -> Option<(&'a str, u32)> {
+    let pick = |skipped: &[String]| {
+        pool.iter()
+            .copied()
+            .filter(|accountx| !skipped.iter().any(|featured| featured == accountx))
+            .min_by_key(|accountx| daily_hash(day, accountx))
+    };
+    match pick(featured) {
+        Some(accountx) => Some((accountx, cycle)),
+        None => Some((pick(&[])?, cycle + 1)),
+    }
+}
+
+fn daily_hash(day: NaiveDate, accountx: &str) -> [u8; 16] {
+    let mut hasher = Md5::new();
+    hasher.update(HASH_SALT.as_bytes());
+    hasher.update(day.format("%Y-%m-%d").to_string().as_bytes());
+    hasher.update(accountx.as_bytes());
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn pool(len: usize) -> Vec<String> {
+        (0..len).map(|i| format!("OC{i:05}")).collect()
+    }
+
+    /// Featuring `days` days from `start` like the table stores them: `(accountx, cycle)`.
+    fn feature(start: NaiveDate, days: usize, pool: &[&str], picks: &mut Vec<(String, u32)>) {
+        for offset in 0..days {
+            let day = start + chrono::Days::new(offset as u64);
+            let cycle = picks.last().map_or(0, |(_, cycle)| *cycle);
+            let featured: Vec<String> = picks
+                .iter()
+                .filter(|(_, pick_cycle)| *pick_cycle == cycle)
+                .map(|(accountx, _)| accountx.to_owned())
+                .collect();
+            let (accountx, cycle) = daily_pick(day, pool, cycle, &featured).unwrap();
+            picks.push((accountx.to_owned(), cycle));
+        }
+    }
+
+    fn distinct(picks: &[(String, u32)]) -> usize {
+        picks
+            .iter()
+            .map(|(accountx, _)| accountx)
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    #[test]
+    fn daily_pick_deterministic_test() {
+        let day = NaiveDate::from_ymd_opt(2024, 5, 17).unwrap();
+        let ocs = pool(57);
+        let mut accountxs: Vec<&str> = ocs.iter().map(|oc| oc.as_str()).collect();
+        let pick = daily_pick(day, &accountxs, 0, &[]);
+        // the order of the pool doesn't matter
+        accountxs.reverse();
+        assert_eq!(daily_pick(day, &accountxs, 0, &[]), pick);
+        assert_eq!(daily_pick(day, &[], 0, &[]), None);
+        assert_eq!(daily_pick(day, &["ONLYONE"], 3, &[]), Some(("ONLYONE", 3)));
+        assert_eq!(
+            daily_pick(day, &["ONLYONE"], 3, &["ONLYONE".to_owned()]),
+            Some(("ONLYONE", 4))
+        );
+    }
+
+    #[test]
+    fn daily_pick_no_repeat_test() {
+        let ocs = pool(30);
+        let accountxs: Vec<&str> = ocs.iter().map(|oc| oc.as_str()).collect();
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+
+        // two full cycles, each one features every OC once
+        let mut picks = Vec::new();
+        feature(start, 60, &accountxs, &mut picks);
+        for cycle in picks.chunks(30) {
+            assert_eq!(distinct(cycle), 30);
+        }
+        assert_eq!(picks[29].1, 0);
+        assert_eq!(picks[30].1, 1);
+    }
+
+    #[test]
+    fn daily_pick_pool_change_test() {
+        let ocs = pool(20);
+        let accountxs: Vec<&str> = ocs.iter().map(|oc| oc.as_str()).collect();
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+
+        let mut picks = Vec::new();
+        feature(start, 10, &accountxs[..15], &mut picks);
+        // 5 new OCs in the middle of the cycle: the featured ones are still skipped
+        feature(start + chrono::Days::new(10), 15, &accountxs, &mut picks);
+        assert_eq!(distinct(&picks[..20]), 20);
+        assert!(picks[..20].iter().all(|(_, cycle)| *cycle == 0));
+        assert_eq!(distinct(&picks[20..]), 5);
+
+        // 10 OCs removed: the cycle ends when the rest was featured
+        let mut picks = Vec::new();
+        feature(start, 5, &accountxs, &mut picks);
+        let rest: Vec<&str> = accountxs
+            .iter()
+            .copied()
+            .filter(|accountx| !picks.iter().any(|(featured, _)| featured == accountx))
+            .take(10)
+            .collect();
+        feature(start + chrono::Days::new(5), 11, &rest, &mut picks);
+        assert_eq!(distinct(&picks[5..15]), 10);
+        assert_eq!(picks[15].1, 1);
+    }
+}
@@ -0,0 +1,303 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::gachaplus_database::oc_ranking_table::OcRankingRow;
+
+/// How many hourly buckets are kept in memory *(one week)*.
+///
+/// *(The older `oc_ranking` rows are compacted into `oc_ranking_total`.)*
+pub const KEPT_HOURS: u32 = 24 * 7;
+/// How many of the all-time top OCs are kept in memory.
+pub const ALL_TIME_KEPT: u32 = 100;
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RankingWindow {
+    Day,
+    Week,
+    All,
+}
+impl RankingWindow {
+    /// Number of hourly buckets in the window *(`None` means all-time)*.
+    pub fn hours(&self) -> Option<u32> {
+        match self {
+            RankingWindow::Day => Some(24),
+            RankingWindow::Week => Some(KEPT_HOURS),
+            RankingWindow::All => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct RankingEntry {
+    pub rank: usize,
+    pub accountx: String,
+    pub imports: u64,
+}
+
+#[derive(Default)]
+struct RankingCounters {
+    /// `hour` => `accountx` => `imports`
+    hours: HashMap<u32, HashMap<String, u32>>,
+    /// The all-time top `(accountx, imports)` from the database, refreshed after every snapshot
+    all_time: Vec<(String, u64)>,
+    /// `(accountx, hour)` pairs changed since the last snapshot
+    dirty: HashSet<(String, u32)>,
+    /// Opted-out or moderated `accountx`s
+    excluded: HashSet<String>,
+    /// `(importer, accountx)` pairs already counted in `importers_hour`
+    importers: HashSet<(u32, String)>,
+    importers_hour: u32,
+}
+
+impl RankingCounters {
+    fn sums(&self, window: RankingWindow, hour: u32) -> HashMap<String, u64> {
+        match window.hours() {
+            Some(hours) => {
+                let mut sums: HashMap<String, u64> = HashMap::new();
+                for (_, bucket) in self
+                    .hours
+                    .iter()
+                    .filter(|(bucket_hour, _)| **bucket_hour + hours > hour)
+                {
+                    for (accountx, imports) in bucket {
+                        *sums.entry(accountx.to_owned()).or_default() += *imports as u64;
+                    }
+                }
+                sums
+            }
+            None => self.all_time.iter().cloned().collect(),
+        }
+    }
+}
+
+/// Counting the successful OC imports in hourly buckets for the leaderboards.
+#[derive(Default)]
+pub struct OcRanking {
+    counters: Mutex<RankingCounters>,
+}
+
+/// Hours since the unix epoch.
+pub fn current_hour() -> u32 {
+    (Utc::now().timestamp() / 3600) as u32
+}
+
+impl OcRanking {
+    /// Loading the last snapshot *(the all-time top and the hourly buckets of the last week)*.
+    pub async fn load(&self, all_time: Vec<(String, u64)>, hours: Vec<OcRankingRow>) {
+        let mut counters = self.counters.lock().await;
+        counters.all_time = all_time;
+        for row in hours {
+            *counters
+                .hours
+                .entry(row.hour)
+                .or_default()
+                .entry(row.accountx)
+                .or_default() += row.imports;
+        }
+    }
+
+    /// Counting an import, once per `importer` *(hashed IP)* and OC in an hour.
+    pub async fn record(&self, accountx: &str, importer: u32) {
+        self.record_at(accountx, importer, current_hour()).await;
+    }
+
+    async fn record_at(&self, accountx: &str, importer: u32, hour: u32) {
+        let mut counters = self.counters.lock().await;
+        if counters.importers_hour != hour {
+            counters.importers.clear();
+            counters.importers_hour = hour;
+        }
+        if !counters.importers.insert((importer, accountx.to_owned())) {
+            return;
+        }
+        *counters
+            .hours
+            .entry(hour)
+            .or_default()
+            .entry(accountx.to_owned())
+            .or_default() += 1;
+        counters.dirty.insert((accountx.to_owned(), hour));
+    }
+
+    pub async fn set_all_time(&self, all_time: Vec<(String, u64)>) {
+        self.counters.lock().await.all_time = all_time;
+    }
+
+    pub async fn set_excluded(&self, excluded: HashSet<String>) {
+        self.counters.lock().await.excluded = excluded;
+    }
+
+    pub async fn top(&self, window: RankingWindow, limit: usize) -> Vec<RankingEntry> {
+        self.top_at(window, limit, current_hour()).await
+    }
+
+    async fn top_at(&self, window: RankingWindow, limit: usize, hour: u32) -> Vec<RankingEntry> {
+        let counters = self.counters.lock().await;
+
+        let mut sums: Vec<(String, u64)> = counters.sums(window, hour).into_iter().collect();
+        sums.retain(|(accountx, _)| !counters.excluded.contains(accountx));
+        sums.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        sums.into_iter()
+            .take(limit)
+            .enumerate()
+            .map(|(index, (accountx, imports))| RankingEntry {
+                rank: index + 1,
+                accountx,
+                imports,
+            })
+            .collect()
+    }
+
+    /// Imports of every OC in the window *(including the excluded ones)*.
+    pub async fn imports(&self, window: RankingWindow) -> HashMap<String, u64> {
+        self.counters.lock().await.sums(window, current_hour())
+    }
+
+    /// Taking the changed buckets for the snapshot and dropping the buckets older than a week.
+    ///
+    /// *(After this no row older than `hour - KEPT_HOURS` is written, so those can be compacted.)*
+    pub async fn take_snapshot_at(&self, hour: u32) -> Vec<OcRankingRow> {
+        let mut counters = self.counters.lock().await;
+        let dirty: Vec<(String, u32)> = counters.dirty.drain().collect();
+        let rows = dirty
+            .into_iter()
+            .filter_map(|(accountx, bucket_hour)| {
+                let imports = *counters.hours.get(&bucket_hour)?.get(&accountx)?;
+                Some(OcRankingRow {
+                    accountx,
+                    hour: bucket_hour,
+                    imports,
+                })
+            })
+            .collect();
+        counters
+            .hours
+            .retain(|bucket_hour, _| *bucket_hour + KEPT_HOURS > hour);
+        rows
+    }
+
+    /// Putting back the rows of a failed snapshot, so the next one retries them.
+    pub async fn restore_dirty(&self, rows: Vec<OcRankingRow>) {
+        let mut counters = self.counters.lock().await;
+        for row in rows {
+            counters.dirty.insert((row.accountx, row.hour));
+        }
+    }
+
+    /// Number of OCs imported in the kept hours.
+    pub async fn len(&self) -> usize {
+        let counters = self.counters.lock().await;
+        counters
+            .hours
+            .values()
+            .flat_map(|bucket| bucket.keys())
+            .collect::<HashSet<&String>>()
+            .len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn ranking_windows_test() {
+        let ranking = OcRanking::default();
+        let now = 1_000_000;
+        ranking.record_at("AAAAAAA", 1, now).await;
+        ranking.record_at("AAAAAAA", 1, now - 30).await;
+        ranking.record_at("AAAAAAA", 2, now - 30).await;
+        ranking.record_at("BBBBBBB", 1, now).await;
+        ranking.record_at("BBBBBBB", 1, now - 1).await;
+        ranking.record_at("CCCCCCC", 1, now - 500).await;
+
+        let day = ranking.top_at(RankingWindow::Day, 10, now).await;
+        assert_eq!(day.len(), 2);
+        assert_eq!(day[0].accountx, "BBBBBBB");
+        assert_eq!(day[0].imports, 2);
+        assert_eq!(day[1].accountx, "AAAAAAA");
+        assert_eq!(day[1].imports, 1);
+
+        let week = ranking.top_at(RankingWindow::Week, 10, now).await;
+        assert_eq!(week[0].accountx, "AAAAAAA");
+        assert_eq!(week[0].imports, 3);
+        assert_eq!(week.len(), 2);
+
+        // the all-time top comes from the database
+        ranking
+            .set_all_time(vec![("CCCCCCC".to_owned(), 4), ("AAAAAAA".to_owned(), 3)])
+            .await;
+        let all = ranking.top_at(RankingWindow::All, 1, now).await;
+        assert_eq!(
+            all,
+            vec![RankingEntry {
+                rank: 1,
+                accountx: "CCCCCCC".to_owned(),
+                imports: 4
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn ranking_excluded_test() {
+        let ranking = OcRanking::default();
+        ranking.record_at("AAAAAAA", 1, 10).await;
+        ranking.record_at("AAAAAAA", 2, 10).await;
+        ranking.record_at("BBBBBBB", 1, 10).await;
+        ranking
+            .set_excluded(HashSet::from(["AAAAAAA".to_owned()]))
+            .await;
+
+        let day = ranking.top_at(RankingWindow::Day, 10, 10).await;
+        assert_eq!(day.len(), 1);
+        assert_eq!(day[0].accountx, "BBBBBBB");
+    }
+
+    #[tokio::test]
+    async fn ranking_dedup_test() {
+        let ranking = OcRanking::default();
+        // the same importer is counted once per OC in an hour
+        for _ in 0..5 {
+            ranking.record_at("AAAAAAA", 1, 10).await;
+        }
+        ranking.record_at("BBBBBBB", 1, 10).await;
+        ranking.record_at("AAAAAAA", 2, 10).await;
+        ranking.record_at("AAAAAAA", 1, 11).await;
+
+        let day = ranking.top_at(RankingWindow::Day, 10, 11).await;
+        assert_eq!(day[0].accountx, "AAAAAAA");
+        assert_eq!(day[0].imports, 3);
+        assert_eq!(day[1].imports, 1);
+        assert_eq!(ranking.len().await, 2);
+    }
+
+    #[tokio::test]
+    async fn ranking_snapshot_test() {
+        let ranking = OcRanking::default();
+        ranking.record_at("AAAAAAA", 1, 10).await;
+        ranking.record_at("AAAAAAA", 2, 10).await;
+        ranking.record_at("AAAAAAA", 1, 11).await;
+
+        let rows = ranking.take_snapshot_at(11).await;
+        assert_eq!(rows.len(), 2);
+        assert!(rows.iter().any(|row| row.hour == 10 && row.imports == 2));
+        assert!(ranking.take_snapshot_at(11).await.is_empty());
+
+        // old buckets are dropped, and aren't written again
+        ranking.record_at("AAAAAAA", 1, 11 + KEPT_HOURS).await;
+        let new_rows = ranking.take_snapshot_at(11 + KEPT_HOURS).await;
+        assert_eq!(new_rows.len(), 1);
+        assert_eq!(new_rows[0].hour, 11 + KEPT_HOURS);
+        ranking.restore_dirty(rows).await;
+        assert!(ranking.take_snapshot_at(11 + KEPT_HOURS).await.is_empty());
+        let week = ranking
+            .top_at(RankingWindow::Week, 10, 11 + KEPT_HOURS)
+            .await;
+        assert_eq!(week[0].imports, 1);
+    }
+}
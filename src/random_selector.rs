@@ -0,0 +1,353 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use rand::{distributions::WeightedIndex, prelude::Distribution, Rng};
+use tokio::sync::{Mutex, RwLock};
+
+use crate::{gachaplus_database::free_oc_table::FreeOc, settings::Settings};
+
+/// Half-life of the recency weight in days.
+const RECENCY_HALF_LIFE_DAYS: f64 = 30.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectorConfig {
+    /// How many recently served OCs are skipped per client.
+    pub history_size: usize,
+    /// How long a client's history is kept after its last request.
+    pub history_duration: Duration,
+    pub popularity_weight: f64,
+    pub recency_weight: f64,
+    pub boost_weight: f64,
+}
+impl Default for SelectorConfig {
+    fn default() -> Self {
+        Self {
+            history_size: 10,
+            history_duration: Duration::from_secs(30 * 60),
+            popularity_weight: 0.0,
+            recency_weight: 0.0,
+            boost_weight: 0.0,
+        }
+    }
+}
+impl SelectorConfig {
+    pub async fn from_settings(settings: &Settings) -> Self {
+        let default = Self::default();
+        Self {
+            history_size: settings
+                .get("random_history_size", default.history_size)
+                .await,
+            history_duration: Duration::from_secs(
+                settings
+                    .get(
+                        "random_history_minutes",
+                        default.history_duration.as_secs() / 60,
+                    )
+                    .await
+                    * 60,
+            ),
+            popularity_weight: settings
+                .get("random_weight_popularity", default.popularity_weight)
+                .await,
+            recency_weight: settings
+                .get("random_weight_recency", default.recency_weight)
+                .await,
+            boost_weight: settings
+                .get("random_weight_boost", default.boost_weight)
+                .await,
+        }
+    }
+}
+
+struct RecentHistory {
+    accountxs: VecDeque<String>,
+    last_used: Instant,
+}
+
+/// Choosing random free OCs with optional weights, without repeating the recently served ones.
+#[derive(Default)]
+pub struct RandomSelector {
+    /// hashed `IP` => recently served `accountx`s
+    history: Mutex<HashMap<u32, RecentHistory>>,
+    /// `accountx` => imports in the last week
+    popularity: RwLock<HashMap<String, u64>>,
+}
+
+impl RandomSelector {
+    pub async fn set_popularity(&self, popularity: HashMap<String, u64>) {
+        *self.popularity.write().await = popularity;
+    }
+
+    pub async fn choose<'a>(
+        &self,
+        client: Option<u32>,
+        ocs: &[&'a FreeOc],
+        config: &SelectorConfig,
+    ) -> Option<&'a FreeOc> {
+        self.choose_multiple(client, ocs, 1, config).await.pop()
+    }
+
+    /// Choosing up to `count` different OCs, each one like `choose`.
+    ///
+    /// *(The history lock is only held to copy and to update the client's history.)*
+    pub async fn choose_multiple<'a>(
+        &self,
+        client: Option<u32>,
+        ocs: &[&'a FreeOc],
+        count: usize,
+        config: &SelectorConfig,
+    ) -> Vec<&'a FreeOc> {
+        let popularity = self.popularity.read().await;
+        let now = Utc::now();
+        let weights: Vec<f64> = ocs
+            .iter()
+            .map(|oc| weight(oc, &popularity, config, now))
+            .collect();
+        drop(popularity);
+
+        let mut recent = match client {
+            Some(client) => self
+                .history
+                .lock()
+                .await
+                .get(&client)
+                .map(|recent| recent.accountxs.clone())
+                .unwrap_or_default(),
+            None => VecDeque::new(),
+        };
+
+        let mut chosen = Vec::new();
+        {
+            let mut rng = rand::thread_rng();
+            let mut taken = HashSet::new();
+            for _ in 0..count.min(ocs.len()) {
+                let Some(index) = pick(ocs, &weights, &recent, &taken, &mut rng) else {
+                    break;
+                };
+                taken.insert(index);
+                recent.push_back(ocs[index].accountx.to_owned());
+                chosen.push(ocs[index]);
+            }
+        }
+
+        if let Some(client) = client {
+            let mut history = self.history.lock().await;
+            let history = history.entry(client).or_insert_with(|| RecentHistory {
+                accountxs: VecDeque::new(),
+                last_used: Instant::now(),
+            });
+            history
+                .accountxs
+                .extend(chosen.iter().map(|oc| oc.accountx.to_owned()));
+            history.last_used = Instant::now();
+            while history.accountxs.len() > config.history_size {
+                history.accountxs.pop_front();
+            }
+        }
+        chosen
+    }
+
+    /// Removing the histories of the inactive clients, returning the count of the removed ones.
+    pub async fn cleanup(&self, max_age: Duration) -> usize {
+        let mut history = self.history.lock().await;
+        let count_before = history.len();
+        history.retain(|_, recent| recent.last_used.elapsed() < max_age);
+        count_before - history.len()
+    }
+
+    pub async fn len(&self) -> usize {
+        self.history.lock().await.len()
+    }
+}
+
+pub fn weight(
+    oc: &FreeOc,
+    popularity: &HashMap<String, u64>,
+    config: &SelectorConfig,
+    now: DateTime<Utc>,
+) -> f64 {
+    let imports = popularity.get(&oc.accountx).copied().unwrap_or_default();
+    let age_in_days = (now - oc.createdate).num_hours().max(0) as f64 / 24.0;
+
+    let weight = 1.0
+        + config.popularity_weight * (imports as f64).ln_1p()
+        + config.recency_weight * 0.5f64.powf(age_in_days / RECENCY_HALF_LIFE_DAYS)
+        + config.boost_weight * oc.boost as f64;
+    if weight.is_finite() {
+        weight.max(0.0)
+    } else {
+        1.0
+    }
+}
+
+/// Picking the index of a weighted random OC, skipping the `recent` ones while there is anything else left.
+///
+/// *(The `taken` ones are always skipped.)*
+fn pick<R: Rng>(
+    ocs: &[&FreeOc],
+    weights: &[f64],
+    recent: &VecDeque<String>,
+    taken: &HashSet<usize>,
+    rng: &mut R,
+) -> Option<usize> {
+    let free: Vec<usize> = (0..ocs.len())
+        .filter(|index| !taken.contains(index))
+        .collect();
+    let mut candidates: Vec<usize> = free
+        .iter()
+        .copied()
+        .filter(|index| !recent.contains(&ocs[*index].accountx))
+        .collect();
+    if candidates.is_empty() {
+        // every OC was served recently, so only the last one is skipped
+        candidates = free
+            .iter()
+            .copied()
+            .filter(|index| recent.back() != Some(&ocs[*index].accountx))
+            .collect();
+    }
+    if candidates.is_empty() {
+        candidates = free;
+    }
+
+    let index = match WeightedIndex::new(candidates.iter().map(|index| weights[*index])) {
+        Ok(distribution) => candidates[distribution.sample(rng)],
+        // all weights are zero
+        Err(_) => *candidates.get(rng.gen_range(0..candidates.len().max(1)))?,
+    };
+    Some(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn free_oc(accountx: &str, boost: u8) -> FreeOc {
+        FreeOc {
+            accountx: accountx.to_owned(),
+            owner: 0,
+            secretid: "SECRETID0".to_owned(),
+            mycode: String::new(),
+            boost,
+            position: 0,
+            createdate: Utc::now(),
+            updatedate: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn random_no_repeat_test() {
+        let selector = RandomSelector::default();
+        let ocs: Vec<FreeOc> = (0..5).map(|i| free_oc(&format!("OC{i}"), 0)).collect();
+        let config = SelectorConfig {
+            history_size: 4,
+            ..Default::default()
+        };
+
+        let ocs: Vec<&FreeOc> = ocs.iter().collect();
+
+        let mut served = HashSet::new();
+        for _ in 0..5 {
+            let oc = selector.choose(Some(1), &ocs, &config).await.unwrap();
+            assert!(served.insert(oc.accountx.to_owned()));
+        }
+        // the pool is exhausted, but the last one still isn't repeated
+        let last = selector.history.lock().await[&1].accountxs.back().cloned();
+        let oc = selector.choose(Some(1), &ocs, &config).await.unwrap();
+        assert_ne!(Some(oc.accountx.to_owned()), last);
+    }
+
+    #[tokio::test]
+    async fn random_single_oc_test() {
+        let selector = RandomSelector::default();
+        let oc = free_oc("ONLYONE", 0);
+        let ocs = vec![&oc];
+        let config = SelectorConfig::default();
+        for _ in 0..3 {
+            let oc = selector.choose(Some(1), &ocs, &config).await;
+            assert_eq!(oc.unwrap().accountx, "ONLYONE");
+        }
+        assert!(selector.choose(Some(1), &[], &config).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn random_batch_test() {
+        let selector = RandomSelector::default();
+        let ocs: Vec<FreeOc> = (0..6).map(|i| free_oc(&format!("OC{i}"), 0)).collect();
+        let ocs: Vec<&FreeOc> = ocs.iter().collect();
+        let config = SelectorConfig {
+            history_size: 3,
+            ..Default::default()
+        };
+
+        // different OCs in a batch, at most the whole pool
+        let batch = selector.choose_multiple(Some(1), &ocs, 4, &config).await;
+        let accountxs: HashSet<&str> = batch.iter().map(|oc| oc.accountx.as_str()).collect();
+        assert_eq!(accountxs.len(), 4);
+        assert_eq!(
+            selector
+                .choose_multiple(None, &ocs, 100, &config)
+                .await
+                .len(),
+            6
+        );
+
+        // the batch is in the history, so the next batch skips it while it can
+        let recent: Vec<String> = selector.history.lock().await[&1]
+            .accountxs
+            .iter()
+            .cloned()
+            .collect();
+        assert_eq!(recent.len(), 3);
+        let next = selector.choose_multiple(Some(1), &ocs, 3, &config).await;
+        assert!(next.iter().all(|oc| !recent.contains(&oc.accountx)));
+
+        // no history without a client
+        assert_eq!(selector.len().await, 1);
+    }
+
+    #[test]
+    fn random_weight_test() {
+        let config = SelectorConfig {
+            boost_weight: 2.0,
+            ..Default::default()
+        };
+        let popularity = HashMap::new();
+        let now = Utc::now();
+        assert_eq!(
+            weight(&free_oc("NOBOOST", 0), &popularity, &config, now),
+            1.0
+        );
+        assert_eq!(
+            weight(&free_oc("BOOSTED", 3), &popularity, &config, now),
+            7.0
+        );
+
+        let config = SelectorConfig {
+            popularity_weight: 1.0,
+            ..Default::default()
+        };
+        let popularity = HashMap::from([("POPULAR".to_owned(), 100)]);
+        assert!(weight(&free_oc("POPULAR", 0), &popularity, &config, now) > 5.0);
+    }
+
+    #[test]
+    fn random_weighted_pick_test() {
+        let (zero, one) = (free_oc("ZERO000", 0), free_oc("ONE0000", 0));
+        let ocs = vec![&zero, &one];
+        let mut rng = rand::thread_rng();
+        for _ in 0..20 {
+            let index = pick(
+                &ocs,
+                &[0.0, 1.0],
+                &VecDeque::new(),
+                &HashSet::new(),
+                &mut rng,
+            );
+            assert_eq!(ocs[index.unwrap()].accountx, "ONE0000");
+        }
+    }
+}
@@ -0,0 +1,392 @@
+use std::{
+    collections::HashMap,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use axum::body::Body;
+use http_body_util::BodyExt;
+use md5::{Digest, Md5};
+use tokio::{io::AsyncWriteExt, sync::RwLock};
+
+use crate::gachaplus_database::latestversion_table::LatestVersionRow;
+
+/// Directory of the published release binaries *(served under `/releases`, next to `files/`)*
+pub const RELEASE_DIR: &str = "releases";
+/// Largest release binary accepted by `/release/publish`
+pub const MAX_RELEASE_SIZE: u64 = 512 * 1024 * 1024;
+
+/// File name of a release, like `1.2.0-Windows-64-stable.exe` *(`all`/`any` for the missing dimensions)*.
+pub fn release_file_name(
+    version: &str,
+    platform: Option<&str>,
+    xbits: Option<u8>,
+    channel: &str,
+    upload_name: &str,
+) -> Result<String, String> {
+    let extension = Path::new(upload_name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .filter(|extension| is_safe(extension))
+        .ok_or_else(|| format!("Invalid file extension: '{upload_name}'"))?;
+    let name = format!(
+        "{version}-{}-{}-{channel}.{extension}",
+        platform.unwrap_or("all"),
+        xbits.map_or("any".to_owned(), |xbits| xbits.to_string()),
+    );
+    if !is_safe(&name) {
+        return Err(format!("Invalid file name: '{name}'"));
+    }
+    Ok(name)
+}
+
+/// Checksum of a stored release, read in chunks *(blocking, the binaries can be big)*.
+pub fn file_checksum(path: &Path) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Md5::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(checksum_hex(hasher))
+}
+
+/// Checksum of a release *(`MD5` hex, like the hand pasted ones of `latestversion`)*.
+///
+/// *(The client code isn't in this tree, so it's unconfirmed that the client checks this format.)*
+fn checksum_hex(hasher: Md5) -> String {
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Name of the release file hosted here, from the `url` of its row.
+pub fn release_name_from_url(url: &str) -> Option<&str> {
+    let (_, name) = url.rsplit_once(&format!("/{RELEASE_DIR}/"))?;
+    is_safe(name).then_some(name)
+}
+
+pub fn release_path(name: &str) -> PathBuf {
+    Path::new(RELEASE_DIR).join(name)
+}
+
+/// Only `[A-Za-z0-9._-]`, so the name can't leave the release directory.
+fn is_safe(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+}
+
+/// Streaming the release `body` into `dir/name` through a temporary file, returning its path and checksum.
+///
+/// The temporary file is unique, and it's linked to the final name only if that doesn't exist, so a half
+/// written binary is never served and a parallel upload of the same release can't overwrite it. Bodies over
+/// `max_len` bytes are rejected while they're read.
+pub async fn store_release(
+    dir: &Path,
+    name: &str,
+    body: Body,
+    max_len: u64,
+) -> std::io::Result<(PathBuf, String)> {
+    tokio::fs::create_dir_all(dir).await?;
+    let path = dir.join(name);
+    let temp_path = dir.join(format!(".{name}.{:016x}.tmp", rand::random::<u64>()));
+    let mut file = tokio::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&temp_path)
+        .await?;
+    let result = async {
+        let checksum = write_body(&mut file, body, max_len).await?;
+        file.sync_all().await?;
+        // fails if the release exists, unlike a rename
+        tokio::fs::hard_link(&temp_path, &path)
+            .await
+            .map_err(|error| match error.kind() {
+                std::io::ErrorKind::AlreadyExists => std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    format!("'{name}' is already published"),
+                ),
+                _ => error,
+            })?;
+        Ok(checksum)
+    }
+    .await;
+    let _ = tokio::fs::remove_file(&temp_path).await;
+    result.map(|checksum| (path, checksum))
+}
+
+/// Writing the `body` into the `file` while hashing it.
+async fn write_body(
+    file: &mut tokio::fs::File,
+    body: Body,
+    max_len: u64,
+) -> std::io::Result<String> {
+    let mut body = body;
+    let mut hasher = Md5::new();
+    let mut len = 0u64;
+    while let Some(frame) = body.frame().await {
+        let frame = frame.map_err(std::io::Error::other)?;
+        let Ok(data) = frame.into_data() else {
+            continue;
+        };
+        len += data.len() as u64;
+        if len > max_len {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::FileTooLarge,
+                format!("The release is over {max_len} bytes"),
+            ));
+        }
+        hasher.update(&data);
+        file.write_all(&data).await?;
+    }
+    if len == 0 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "Empty release file",
+        ));
+    }
+    Ok(checksum_hex(hasher))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReleaseFileState {
+    /// Matches the checksum of its row *(also its `ETag`)*
+    Verified(String),
+    /// Differs from the checksum of its row, so it isn't served
+    Corrupt,
+}
+
+/// The hosted release files by name, only the verified ones are served.
+#[derive(Default)]
+pub struct ReleaseIndex {
+    files: RwLock<HashMap<String, ReleaseFileState>>,
+}
+
+impl ReleaseIndex {
+    pub async fn get(&self, name: &str) -> Option<ReleaseFileState> {
+        self.files.read().await.get(name).cloned()
+    }
+
+    pub async fn insert(&self, name: String, state: ReleaseFileState) {
+        self.files.write().await.insert(name, state);
+    }
+
+    pub async fn remove(&self, name: &str) {
+        self.files.write().await.remove(name);
+    }
+
+    /// Checking the hosted files of the rows against their checksums, returning the names of the corrupt ones.
+    ///
+    /// *(The files of the rolled back releases aren't served.)*
+    pub async fn verify(&self, rows: &[LatestVersionRow]) -> Vec<String> {
+        let mut corrupt = Vec::new();
+        for row in rows.iter().filter(|row| row.rollbackdate.is_none()) {
+            let Some(name) = release_name_from_url(&row.url) else {
+                continue;
+            };
+            let path = release_path(name);
+            if !path.is_file() {
+                continue;
+            }
+            let checksum = tokio::task::spawn_blocking(move || file_checksum(&path))
+                .await
+                .ok()
+                .and_then(|checksum| checksum.ok());
+            let state = match checksum {
+                Some(checksum) if checksum.eq_ignore_ascii_case(&row.checksum) => {
+                    ReleaseFileState::Verified(checksum)
+                }
+                _ => {
+                    corrupt.push(name.to_owned());
+                    ReleaseFileState::Corrupt
+                }
+            };
+            self.insert(name.to_owned(), state).await;
+        }
+        corrupt
+    }
+
+    /// The verified and the corrupt files.
+    pub async fn counts(&self) -> (usize, usize) {
+        let files = self.files.read().await;
+        let corrupt = files
+            .values()
+            .filter(|state| **state == ReleaseFileState::Corrupt)
+            .count();
+        (files.len() - corrupt, corrupt)
+    }
+}
+
+/// A `Range` request header against a file of `len` bytes.
+#[derive(Debug, PartialEq)]
+pub enum ByteRange {
+    /// No *(usable)* range, the whole file
+    Full,
+    /// `start..=end`
+    Partial(u64, u64),
+    /// Out of the file *(`416`)*
+    Unsatisfiable,
+}
+
+/// Parsing a single `bytes=` range, the multiple ranges are served as the whole file.
+pub fn parse_range(header: Option<&str>, len: u64) -> ByteRange {
+    let Some(range) = header.and_then(|header| header.trim().strip_prefix("bytes=")) else {
+        return ByteRange::Full;
+    };
+    if range.contains(',') {
+        return ByteRange::Full;
+    }
+    let Some((start, end)) = range.trim().split_once('-') else {
+        return ByteRange::Full;
+    };
+    let (start, end) = match (start.trim(), end.trim()) {
+        // the last `suffix` bytes
+        ("", suffix) => match suffix.parse::<u64>() {
+            Ok(0) => return ByteRange::Unsatisfiable,
+            Ok(suffix) => (len.saturating_sub(suffix), len.saturating_sub(1)),
+            Err(_) => return ByteRange::Full,
+        },
+        (start, "") => match start.parse::<u64>() {
+            Ok(start) => (start, len.saturating_sub(1)),
+            Err(_) => return ByteRange::Full,
+        },
+        (start, end) => match (start.parse::<u64>(), end.parse::<u64>()) {
+            (Ok(start), Ok(end)) if start <= end => (start, end.min(len.saturating_sub(1))),
+            _ => return ByteRange::Full,
+        },
+    };
+    if len == 0 || start >= len {
+        return ByteRange::Unsatisfiable;
+    }
+    ByteRange::Partial(start, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checksum(bytes: &[u8]) -> String {
+        let mut hasher = Md5::new();
+        hasher.update(bytes);
+        checksum_hex(hasher)
+    }
+
+    #[test]
+    fn release_checksum_test() {
+        assert_eq!(checksum(b""), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(checksum(b"gacha plus"), checksum(b"gacha plus"));
+        assert_ne!(checksum(b"1"), checksum(b"2"));
+
+        let path = std::env::temp_dir().join(format!("release-{:016x}", rand::random::<u64>()));
+        std::fs::write(&path, b"gacha plus").unwrap();
+        assert_eq!(file_checksum(&path).unwrap(), checksum(b"gacha plus"));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn release_range_test() {
+        assert_eq!(parse_range(None, 100), ByteRange::Full);
+        assert_eq!(
+            parse_range(Some("bytes=0-9"), 100),
+            ByteRange::Partial(0, 9)
+        );
+        assert_eq!(
+            parse_range(Some("bytes=50-"), 100),
+            ByteRange::Partial(50, 99)
+        );
+        assert_eq!(
+            parse_range(Some("bytes=-10"), 100),
+            ByteRange::Partial(90, 99)
+        );
+        assert_eq!(
+            parse_range(Some("bytes=90-200"), 100),
+            ByteRange::Partial(90, 99)
+        );
+        assert_eq!(
+            parse_range(Some("bytes=100-"), 100),
+            ByteRange::Unsatisfiable
+        );
+        assert_eq!(parse_range(Some("bytes=-0"), 100), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range(Some("bytes=0-1,5-6"), 100), ByteRange::Full);
+        assert_eq!(parse_range(Some("bytes=9-1"), 100), ByteRange::Full);
+        assert_eq!(parse_range(Some("items=0-1"), 100), ByteRange::Full);
+    }
+
+    #[test]
+    fn release_name_from_url_test() {
+        assert_eq!(
+            release_name_from_url("https://gacha-plus.com/releases/1.2.0-all-any-stable.exe"),
+            Some("1.2.0-all-any-stable.exe")
+        );
+        assert_eq!(release_name_from_url("https://example.com/game.exe"), None);
+        assert_eq!(release_name_from_url("https://x/releases/../a.exe"), None);
+    }
+
+    #[test]
+    fn release_file_name_test() {
+        assert_eq!(
+            release_file_name("1.2.0", Some("Windows"), Some(64), "stable", "setup.exe").unwrap(),
+            "1.2.0-Windows-64-stable.exe"
+        );
+        assert_eq!(
+            release_file_name("1.2.0", None, None, "beta", "game.apk").unwrap(),
+            "1.2.0-all-any-beta.apk"
+        );
+        assert!(release_file_name("1.2.0", None, None, "stable", "no_extension").is_err());
+        assert!(release_file_name("1.2.0", None, None, "stable", "x.a/b").is_err());
+        assert!(release_file_name("../1", None, None, "stable", "a.exe").is_err());
+    }
+
+    #[tokio::test]
+    async fn store_release_test() {
+        let dir = std::env::temp_dir().join(format!("releases-{:016x}", rand::random::<u64>()));
+        let (path, stored) = store_release(&dir, "a.exe", Body::from("release"), 100)
+            .await
+            .unwrap();
+        assert_eq!(stored, checksum(b"release"));
+        assert_eq!(std::fs::read(&path).unwrap(), b"release");
+
+        // published once, the parallel uploads of the same release fail
+        let uploads = (0..8).map(|i| {
+            let dir = dir.clone();
+            tokio::spawn(async move {
+                store_release(&dir, "b.exe", Body::from(format!("upload {i}")), 100).await
+            })
+        });
+        let mut stored = Vec::new();
+        for upload in uploads {
+            match upload.await.unwrap() {
+                Ok((_, checksum)) => stored.push(checksum),
+                Err(error) => assert_eq!(error.kind(), std::io::ErrorKind::AlreadyExists),
+            }
+        }
+        assert_eq!(stored.len(), 1);
+        assert_eq!(file_checksum(&dir.join("b.exe")).unwrap(), stored[0]);
+        let error = store_release(&dir, "a.exe", Body::from("other"), 100)
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::AlreadyExists);
+        assert_eq!(std::fs::read(&path).unwrap(), b"release");
+
+        let error = store_release(&dir, "c.exe", Body::from("too long"), 4)
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::FileTooLarge);
+        let error = store_release(&dir, "d.exe", Body::empty(), 4)
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+        // only the published files, no temporary ones
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
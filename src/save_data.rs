@@ -0,0 +1,73 @@
+use crate::character_code::CharacterCode;
+
+/// Kinds of the datastrings of a transfer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SlotKind {
+    Character,
+    /// Stored as it is *(the format isn't known, there are no real client saves to check it against)*
+    Unchecked,
+}
+
+/// The datastring slots sent by the client *(`datastring<slot>`)*.
+///
+/// *(A slot of a new client version only needs a new entry here.)*
+pub const SLOTS: [(u8, SlotKind); 20] = [
+    (1, SlotKind::Unchecked),
+    (2, SlotKind::Unchecked),
+    (3, SlotKind::Unchecked),
+    (4, SlotKind::Unchecked),
+    (5, SlotKind::Unchecked),
+    (6, SlotKind::Unchecked),
+    (7, SlotKind::Unchecked),
+    (8, SlotKind::Unchecked),
+    (9, SlotKind::Character),
+    (10, SlotKind::Character),
+    (11, SlotKind::Character),
+    (12, SlotKind::Character),
+    (13, SlotKind::Character),
+    (14, SlotKind::Character),
+    (15, SlotKind::Character),
+    (16, SlotKind::Character),
+    (17, SlotKind::Character),
+    (18, SlotKind::Character),
+    (19, SlotKind::Unchecked),
+    (20, SlotKind::Unchecked),
+];
+
+impl SlotKind {
+    pub fn from_slot(slot: u8) -> Option<SlotKind> {
+        SLOTS
+            .iter()
+            .find(|(known_slot, _)| *known_slot == slot)
+            .map(|(_, kind)| *kind)
+    }
+
+    pub fn validate(&self, code: &str) -> Result<(), String> {
+        match self {
+            SlotKind::Character => CharacterCode::new_from_code(code).map(|_| ()),
+            SlotKind::Unchecked => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_data_slots_test() {
+        assert_eq!(SlotKind::from_slot(1), Some(SlotKind::Unchecked));
+        assert_eq!(SlotKind::from_slot(9), Some(SlotKind::Character));
+        assert_eq!(SlotKind::from_slot(18), Some(SlotKind::Character));
+        assert_eq!(SlotKind::from_slot(20), Some(SlotKind::Unchecked));
+        assert_eq!(SlotKind::from_slot(0), None);
+        assert_eq!(SlotKind::from_slot(21), None);
+    }
+
+    #[test]
+    fn save_data_validators_test() {
+        assert!(SlotKind::Character.validate("").is_err());
+        assert!(SlotKind::Unchecked.validate("").is_ok());
+        assert!(SlotKind::Unchecked.validate("anything | goes").is_ok());
+    }
+}
@@ -0,0 +1,123 @@
+use std::{collections::HashMap, str::FromStr};
+
+use tokio::sync::RwLock;
+
+use crate::client_version::ClientVersion;
+
+/// Checking that the setting is known and its value parses as the type it's read as.
+pub fn validate(name: &str, value: &str) -> Result<(), String> {
+    let value = value.trim();
+    let is_valid = match name {
+        "freeoc_full_refresh_minutes"
+        | "oc_cache_ttl_seconds"
+        | "random_history_minutes"
+        | "report_hide_threshold" => value.parse::<u64>().is_ok(),
+        "oc_cache_size" | "random_batch_max" | "random_history_size" => {
+            value.parse::<usize>().is_ok()
+        }
+        "random_weight_boost" | "random_weight_popularity" | "random_weight_recency" => {
+            value.parse::<f64>().is_ok()
+        }
+        "transfer_max_age_days" | "transfer_reservation_days" => value.parse::<i64>().is_ok(),
+        "transfer_max_uses" => value.parse::<u16>().is_ok(),
+        "transfer_single_use" => value.parse::<bool>().is_ok(),
+        "transfer_snapshot_count" => value.parse::<u32>().is_ok(),
+        "release_base_url" => url::Url::parse(value).is_ok(),
+        // also `min_client_version:<path>`
+        _ if name == "min_client_version" || name.starts_with("min_client_version:") => {
+            value.parse::<ClientVersion>().is_ok()
+        }
+        _ => return Err(format!("Unknown setting: `{name}`")),
+    };
+    if is_valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid value for `{name}`: '{value}'"))
+    }
+}
+
+/// Runtime settings from the `settings` table, so they can be changed without redeploying.
+#[derive(Default)]
+pub struct Settings {
+    values: RwLock<HashMap<String, String>>,
+}
+
+impl Settings {
+    pub async fn replace(&self, values: HashMap<String, String>) {
+        *self.values.write().await = values;
+    }
+
+    /// Getting a setting, or the `default` if it's missing or can't be parsed.
+    ///
+    /// *(The values are validated when they're written, and the invalid ones are logged by `invalid` at loading.)*
+    pub async fn get<T: FromStr>(&self, name: &str, default: T) -> T {
+        self.values
+            .read()
+            .await
+            .get(name)
+            .and_then(|value| value.trim().parse::<T>().ok())
+            .unwrap_or(default)
+    }
+
+    pub async fn all(&self) -> Vec<(String, String)> {
+        let mut values: Vec<(String, String)> = self
+            .values
+            .read()
+            .await
+            .iter()
+            .map(|(name, value)| (name.to_owned(), value.to_owned()))
+            .collect();
+        values.sort();
+        values
+    }
+
+    /// The errors of the stored settings that are unknown or can't be parsed *(changed in the database directly)*.
+    pub async fn invalid(&self) -> Vec<String> {
+        let mut errors: Vec<String> = self
+            .values
+            .read()
+            .await
+            .iter()
+            .filter_map(|(name, value)| validate(name, value).err())
+            .collect();
+        errors.sort();
+        errors
+    }
+
+    pub async fn len(&self) -> usize {
+        self.values.read().await.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_validate_test() {
+        assert!(validate("transfer_max_uses", "3").is_ok());
+        assert!(validate("transfer_max_uses", " 3 ").is_ok());
+        assert!(validate("transfer_max_uses", "70000").is_err());
+        assert!(validate("transfer_single_use", "yes").is_err());
+        assert!(validate("random_weight_boost", "0.5").is_ok());
+        assert!(validate("min_client_version", "10.10.10").is_ok());
+        assert!(validate("min_client_version:/GPscripts/startup.php", "1.2.3").is_ok());
+        assert!(validate("min_client_version:/GPscripts/startup.php", "1.x").is_err());
+        assert!(validate("release_base_url", "https://gacha-plus.com").is_ok());
+        assert!(validate("unknown_setting", "1").is_err());
+    }
+
+    #[tokio::test]
+    async fn settings_invalid_test() {
+        let settings = Settings::default();
+        settings
+            .replace(HashMap::from([
+                ("transfer_max_uses".to_owned(), "many".to_owned()),
+                ("oc_cache_size".to_owned(), "100".to_owned()),
+            ]))
+            .await;
+        assert_eq!(settings.get("transfer_max_uses", 5u16).await, 5);
+        assert_eq!(settings.get("oc_cache_size", 0usize).await, 100);
+        assert_eq!(settings.invalid().await.len(), 1);
+    }
+}
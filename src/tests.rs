@@ -1,4 +1,5 @@
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests {
     use reqwest::{
         header::{HeaderMap, HeaderValue},
@@ -70,4 +71,169 @@ mod tests {
             panic!("From FreeOC: {}", txt);
         }
     }
+
+    /// Uploads, snapshot trimming, restore and the cleanup of a test transfer, right on the database.
+    #[tokio::test]
+    async fn test_transfer_snapshots() {
+        use crate::gachaplus_database::{
+            tranfer_datas_table::{TransferDatas, TransferWrite},
+            GachaPlusDatabase,
+        };
+
+        const ACCOUNTX: u32 = 999_999_998;
+        _ = dotenv::dotenv();
+        let database =
+            GachaPlusDatabase::new(crate::enviorment::get_enviorment("DATABASE_URL")).await;
+        let table = &database.tranfer_datas_table;
+        let datas = |datastring: &str| {
+            let mut datas = TransferDatas {
+                accountx: ACCOUNTX.to_string(),
+                ..Default::default()
+            };
+            datas.datastrings.insert(1, datastring.to_owned());
+            datas
+        };
+        let snapshot_datas = |snapshots: &[_]| -> Vec<String> {
+            snapshots
+                .iter()
+                .map(|snapshot| {
+                    table
+                        .decode_snapshot(snapshot)
+                        .unwrap()
+                        .datastring(1)
+                        .to_owned()
+                })
+                .collect()
+        };
+        table.delete(ACCOUNTX).await.unwrap();
+        table.delete_orphan_snapshots().await.unwrap();
+
+        // only the last 2 replaced datas are kept
+        for datastring in ["a", "b", "c", "d"] {
+            let written = table
+                .insert_or_update(datas(datastring), None, None, None, 2)
+                .await
+                .unwrap();
+            assert_eq!(written, TransferWrite::Written);
+        }
+        let snapshots = table.get_snapshots(ACCOUNTX).await.unwrap();
+        assert_eq!(snapshot_datas(&snapshots), ["c", "b"]);
+
+        // the restored data is loaded, the replaced one becomes a snapshot
+        assert!(table
+            .restore_snapshot(ACCOUNTX, snapshots[1].id, 2)
+            .await
+            .unwrap());
+        assert_eq!(table.get(ACCOUNTX).await.unwrap().data.datastring(1), "b");
+        let snapshots = table.get_snapshots(ACCOUNTX).await.unwrap();
+        assert_eq!(snapshot_datas(&snapshots), ["d", "c"]);
+        assert!(!table.restore_snapshot(ACCOUNTX, 0, 2).await.unwrap());
+
+        // a partial update keeps the other slots, the PIN and the owner
+        table.delete(ACCOUNTX).await.unwrap();
+        let token = crate::transfer_owner::generate_token();
+        let token_hash = crate::transfer_owner::hash_token(&token);
+        assert!(table.reserve(ACCOUNTX, &token_hash).await.unwrap());
+        let mut full = datas("a");
+        full.datastrings.insert(2, "kept".to_owned());
+        full.datastrings.insert(3, "cleared".to_owned());
+        let pin = Some("pin-hash".to_owned());
+        let written = table
+            .insert_or_update(full, pin.clone(), Some(&token), None, 2)
+            .await
+            .unwrap();
+        assert_eq!(written, TransferWrite::Written);
+        let mut update = datas("b");
+        update.datastrings.insert(3, String::new());
+        assert_eq!(
+            table
+                .merge_update(update.clone(), None, pin.as_deref(), 2)
+                .await
+                .unwrap(),
+            TransferWrite::NotOwner
+        );
+        assert_eq!(
+            table
+                .merge_update(update.clone(), Some(&token), None, 2)
+                .await
+                .unwrap(),
+            TransferWrite::PinChanged
+        );
+        assert_eq!(
+            table
+                .merge_update(update, Some(&token), pin.as_deref(), 2)
+                .await
+                .unwrap(),
+            TransferWrite::Written
+        );
+        let merged = table.get(ACCOUNTX).await.unwrap().data;
+        assert_eq!(merged.datastring(1), "b");
+        assert_eq!(merged.datastring(2), "kept");
+        assert_eq!(merged.datastrings.get(&3).map(|s| s.as_str()), Some(""));
+        let protection = table.get_protection(ACCOUNTX).await.unwrap().unwrap();
+        assert_eq!(protection.pin, pin);
+        assert_eq!(protection.owner_token, Some(token_hash));
+        assert!(table.get_reservation(ACCOUNTX).await.unwrap().is_none());
+
+        // the snapshots go with the transfer
+        table.delete(ACCOUNTX).await.unwrap();
+        table.delete_orphan_snapshots().await.unwrap();
+        assert!(table.get_snapshots(ACCOUNTX).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_moderation_actions() {
+        use crate::gachaplus_database::{
+            oc_moderation_table::{ModerationActionEnum, ModerationStatusEnum},
+            GachaPlusDatabase,
+        };
+
+        const ACCOUNTX: &str = "ZZZTEST";
+        const MODERATOR: u32 = 0x1234_5678;
+        _ = dotenv::dotenv();
+        let database =
+            GachaPlusDatabase::new(crate::enviorment::get_enviorment("DATABASE_URL")).await;
+        let table = &database.oc_moderation_table;
+        let excluded = || async {
+            database
+                .oc_ranking_table
+                .get_excluded()
+                .await
+                .unwrap()
+                .contains(&ACCOUNTX.to_owned())
+        };
+        table.delete(ACCOUNTX, MODERATOR).await.unwrap();
+
+        table.insert_report(ACCOUNTX, "test", 1).await.unwrap();
+        table.insert_report(ACCOUNTX, "test", 2).await.unwrap();
+        table
+            .hide(ACCOUNTX, ModerationActionEnum::Hide, MODERATOR)
+            .await
+            .unwrap();
+        assert_eq!(
+            table.get_status(ACCOUNTX).await.unwrap(),
+            Some(ModerationStatusEnum::Hidden)
+        );
+        assert!(excluded().await);
+
+        table.approve(ACCOUNTX, MODERATOR).await.unwrap();
+        assert_eq!(
+            table.get_status(ACCOUNTX).await.unwrap(),
+            Some(ModerationStatusEnum::Approved)
+        );
+        assert_eq!(table.count_reports(ACCOUNTX).await.unwrap(), 0);
+        assert!(!excluded().await);
+
+        table.delete(ACCOUNTX, MODERATOR).await.unwrap();
+        assert_eq!(table.get_status(ACCOUNTX).await.unwrap(), None);
+        let logs = table.get_logs().await.unwrap();
+        let hide_log = logs
+            .iter()
+            .find(|log| {
+                log.accountx == ACCOUNTX && log.action == ModerationActionEnum::Hide.value()
+            })
+            .unwrap();
+        assert_eq!(hide_log.reports, 2);
+        assert_eq!(hide_log.moderator, MODERATOR);
+    }
 }
@@ -0,0 +1,202 @@
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    Aes256Gcm, Key, Nonce,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::Utc;
+use inline_colorization::*;
+use serde::{Deserialize, Serialize};
+
+/// Encrypted `transfer.data` *(stored as JSON, because of the `json_valid` check of the column)*.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Envelope {
+    kid: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// AES-256-GCM keys of the transfer datas by key id, the first one encrypts.
+///
+/// The keys come from `TRANSFER_KEYS` or the file in `TRANSFER_KEYS_FILE`: `id:base64key` entries separated by
+/// commas or new lines. To rotate, put the new key first and keep the old ones until the re-encryption finishes.
+#[derive(Default)]
+pub struct TransferCrypto {
+    keys: Vec<(String, Aes256Gcm)>,
+}
+
+impl TransferCrypto {
+    /// Loading the keys, without them the transfer datas are stored in plaintext.
+    pub fn from_enviorment() -> Self {
+        let keys = match (
+            std::env::var("TRANSFER_KEYS"),
+            std::env::var("TRANSFER_KEYS_FILE"),
+        ) {
+            (Ok(keys), _) => Ok(keys),
+            (_, Ok(path)) => std::fs::read_to_string(&path)
+                .map_err(|error| format!("Failed to read '{path}': {error}")),
+            _ => {
+                println!("{color_yellow}{}\tTransfer: ⚠️ No `TRANSFER_KEYS`, the transfer datas are stored in plaintext ⚠️{color_white}",
+                    Utc::now().format("[%H:%M:%S]"),
+                );
+                return Self::default();
+            }
+        };
+        match keys.and_then(|keys| Self::parse_keys(&keys)) {
+            Ok(crypto) => {
+                println!("{color_cyan}{}{color_green}\tTransfer: ✅ Loading {} encryption key is successful! ✅{color_white}",
+                    Utc::now().format("[%H:%M:%S]"),
+                    crypto.keys.len()
+                );
+                crypto
+            }
+            Err(error) => {
+                println!(
+                    "{color_red}{}\tTransfer: 🔥 Failed to load the encryption keys: {error} 🔥{color_white}",
+                    Utc::now().format("[%H:%M:%S]"),
+                );
+                std::process::exit(1);
+            }
+        }
+    }
+
+    pub fn parse_keys(text: &str) -> Result<Self, String> {
+        let mut keys: Vec<(String, Aes256Gcm)> = Vec::new();
+        for entry in text
+            .split([',', '\n'])
+            .map(|entry| entry.trim())
+            .filter(|entry| !entry.is_empty())
+        {
+            let (id, key) = entry
+                .split_once(':')
+                .ok_or_else(|| "Key without id".to_owned())?;
+            let id = id.trim();
+            if id.is_empty() || keys.iter().any(|(known_id, _)| known_id == id) {
+                return Err(format!("Invalid or repeated key id: '{id}'"));
+            }
+            let key = BASE64
+                .decode(key.trim())
+                .map_err(|error| format!("Key '{id}' isn't base64: {error}"))?;
+            if key.len() != 32 {
+                return Err(format!("Key '{id}' isn't 32 bytes"));
+            }
+            keys.push((
+                id.to_owned(),
+                Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)),
+            ));
+        }
+        if keys.is_empty() {
+            return Err("No key".to_owned());
+        }
+        Ok(Self { keys })
+    }
+
+    /// Id of the key encrypting the new datas *(`None` if there is no key)*.
+    pub fn active_key_id(&self) -> Option<&str> {
+        self.keys.first().map(|(id, _)| id.as_str())
+    }
+
+    /// Encrypting the data bound to the `accountx`, returning the stored data and the key id.
+    ///
+    /// *(Without keys the data stays plaintext.)*
+    pub fn encrypt(&self, accountx: &str, data: &str) -> Result<(String, Option<String>), String> {
+        let Some((id, cipher)) = self.keys.first() else {
+            return Ok((data.to_owned(), None));
+        };
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: data.as_bytes(),
+                    aad: accountx.as_bytes(),
+                },
+            )
+            .map_err(|_| "Encryption failed".to_owned())?;
+        let envelope = Envelope {
+            kid: id.to_owned(),
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(ciphertext),
+        };
+        let stored = serde_json::to_string(&envelope).map_err(|error| error.to_string())?;
+        Ok((stored, Some(id.to_owned())))
+    }
+
+    /// Decrypting the stored data, the plaintext rows are returned as they are.
+    pub fn decrypt(&self, accountx: &str, stored: &[u8]) -> Result<Vec<u8>, String> {
+        let Ok(envelope) = serde_json::from_slice::<Envelope>(stored) else {
+            return Ok(stored.to_vec());
+        };
+        let (_, cipher) = self
+            .keys
+            .iter()
+            .find(|(id, _)| *id == envelope.kid)
+            .ok_or_else(|| format!("Unknown key id: '{}'", envelope.kid))?;
+        let nonce = BASE64
+            .decode(&envelope.nonce)
+            .map_err(|error| error.to_string())?;
+        if nonce.len() != 12 {
+            return Err("Invalid nonce".to_owned());
+        }
+        let ciphertext = BASE64
+            .decode(&envelope.ciphertext)
+            .map_err(|error| error.to_string())?;
+        cipher
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad: accountx.as_bytes(),
+                },
+            )
+            .map_err(|_| "Decryption failed".to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY1: &str = "MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=";
+    const KEY2: &str = "ZmVkY2JhOTg3NjU0MzIxMGZlZGNiYTk4NzY1NDMyMTA=";
+
+    #[test]
+    fn transfer_crypto_test() {
+        let crypto = TransferCrypto::parse_keys(&format!("k1:{KEY1}")).unwrap();
+        let data = r#"{"accountx":"123456789"}"#;
+        let (stored, key_id) = crypto.encrypt("123456789", data).unwrap();
+        assert_eq!(key_id.as_deref(), Some("k1"));
+        assert!(!stored.contains("accountx"));
+        assert_eq!(
+            crypto.decrypt("123456789", stored.as_bytes()).unwrap(),
+            data.as_bytes()
+        );
+        // bound to the `accountx`
+        assert!(crypto.decrypt("987654321", stored.as_bytes()).is_err());
+        // plaintext rows are kept
+        assert_eq!(
+            crypto.decrypt("123456789", data.as_bytes()).unwrap(),
+            data.as_bytes()
+        );
+    }
+
+    #[test]
+    fn transfer_crypto_rotation_test() {
+        let old = TransferCrypto::parse_keys(&format!("k1:{KEY1}")).unwrap();
+        let (stored, _) = old.encrypt("123456789", "{}").unwrap();
+
+        let rotated = TransferCrypto::parse_keys(&format!("k2:{KEY2}\nk1:{KEY1}")).unwrap();
+        assert_eq!(rotated.active_key_id(), Some("k2"));
+        assert_eq!(
+            rotated.decrypt("123456789", stored.as_bytes()).unwrap(),
+            b"{}"
+        );
+
+        let removed = TransferCrypto::parse_keys(&format!("k2:{KEY2}")).unwrap();
+        assert!(removed.decrypt("123456789", stored.as_bytes()).is_err());
+
+        assert!(TransferCrypto::parse_keys("k1:short").is_err());
+        assert!(TransferCrypto::parse_keys(" \n,").is_err());
+        assert!(TransferCrypto::parse_keys(&format!("k1:{KEY1},k1:{KEY2}")).is_err());
+    }
+}
@@ -0,0 +1,95 @@
+use chrono::{DateTime, TimeDelta, Utc};
+
+use crate::settings::Settings;
+
+/// Largest day count taken from the settings *(a larger one would overflow the dates)*
+pub const MAX_DAYS: i64 = 36_500;
+
+/// Limits of a transfer code *(`0` means unlimited in the settings)*.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TransferExpiry {
+    /// Loads allowed per upload *(`transfer_max_uses`, or `1` with `transfer_single_use`)*
+    pub max_uses: Option<u16>,
+    /// Lifetime since the upload *(`transfer_max_age_days`, at most `MAX_DAYS`)*
+    pub max_age: Option<TimeDelta>,
+}
+
+impl TransferExpiry {
+    pub async fn from_settings(settings: &Settings) -> Self {
+        let max_uses: u16 = settings.get("transfer_max_uses", 0).await;
+        let single_use = settings.get("transfer_single_use", false).await;
+        let max_age_days: i64 = settings.get("transfer_max_age_days", 0).await;
+        Self {
+            max_uses: if single_use {
+                Some(1)
+            } else {
+                Some(max_uses).filter(|max_uses| *max_uses > 0)
+            },
+            max_age: Some(max_age_days)
+                .filter(|days| *days > 0)
+                .and_then(|days| TimeDelta::try_days(days.min(MAX_DAYS))),
+        }
+    }
+
+    /// Upload time before which the transfers are expired, `None` if it's out of the date range.
+    pub fn oldest_allowed(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        now.checked_sub_signed(self.max_age?)
+    }
+
+    pub fn is_expired(&self, used: u16, regdate: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        self.max_uses.is_some_and(|max_uses| used >= max_uses)
+            || self.max_age.is_some_and(|max_age| now - regdate > max_age)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn transfer_expiry_test() {
+        let now = Utc::now();
+        let unlimited = TransferExpiry::default();
+        assert!(!unlimited.is_expired(u16::MAX, now - TimeDelta::days(3650), now));
+
+        let single_use = TransferExpiry {
+            max_uses: Some(1),
+            max_age: None,
+        };
+        assert!(!single_use.is_expired(0, now, now));
+        assert!(single_use.is_expired(1, now, now));
+
+        let week = TransferExpiry {
+            max_uses: None,
+            max_age: Some(TimeDelta::days(7)),
+        };
+        assert!(!week.is_expired(100, now - TimeDelta::days(6), now));
+        assert!(week.is_expired(0, now - TimeDelta::days(8), now));
+        assert_eq!(week.oldest_allowed(now), Some(now - TimeDelta::days(7)));
+        assert_eq!(unlimited.oldest_allowed(now), None);
+    }
+
+    #[tokio::test]
+    async fn transfer_expiry_overflow_test() {
+        let settings = Settings::default();
+        settings
+            .replace(HashMap::from([(
+                "transfer_max_age_days".to_owned(),
+                i64::MAX.to_string(),
+            )]))
+            .await;
+        let expiry = TransferExpiry::from_settings(&settings).await;
+        assert_eq!(expiry.max_age, Some(TimeDelta::days(MAX_DAYS)));
+        let now = Utc::now();
+        assert!(expiry.oldest_allowed(now).is_some());
+        assert!(!expiry.is_expired(0, now - TimeDelta::days(365), now));
+
+        // out of the date range, but no panic
+        let huge = TransferExpiry {
+            max_uses: None,
+            max_age: Some(TimeDelta::MAX),
+        };
+        assert_eq!(huge.oldest_allowed(now), None);
+    }
+}
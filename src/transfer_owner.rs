@@ -0,0 +1,64 @@
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+/// Range of the `accountx` numbers
+pub const ACCOUNTX_RANGE: std::ops::RangeInclusive<u32> = 100_000_000..=999_999_999;
+
+/// Random `accountx` for the allocation.
+pub fn random_accountx() -> u32 {
+    rand::thread_rng().gen_range(ACCOUNTX_RANGE)
+}
+
+/// Random owner token *(64 hex characters)*, only its hash is stored.
+pub fn generate_token() -> String {
+    let bytes: [u8; 32] = rand::thread_rng().gen();
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+pub fn hash_token(token: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(token.trim());
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Checking the `token` against the hashes of the reservation and the owner of the transfer.
+///
+/// *(A number without reservation and owner is free for everyone, like before the allocation.)*
+pub fn is_owner(reservation: Option<&str>, owner: Option<&str>, token: Option<&str>) -> bool {
+    let token_hash = token.map(hash_token);
+    [reservation, owner]
+        .into_iter()
+        .flatten()
+        .all(|hash| token_hash.as_deref() == Some(hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transfer_owner_test() {
+        let token = generate_token();
+        assert_eq!(token.len(), 64);
+        assert_ne!(token, generate_token());
+        let hash = hash_token(&token);
+        assert_ne!(hash, token);
+        assert!(ACCOUNTX_RANGE.contains(&random_accountx()));
+
+        assert!(is_owner(None, None, None));
+        assert!(is_owner(None, None, Some(&token)));
+        assert!(is_owner(Some(&hash), None, Some(&token)));
+        assert!(is_owner(None, Some(&hash), Some(&token)));
+        assert!(!is_owner(Some(&hash), None, None));
+        assert!(!is_owner(None, Some(&hash), Some("other")));
+        assert!(!is_owner(
+            Some(&hash),
+            Some(&hash_token("other")),
+            Some(&token)
+        ));
+    }
+}
@@ -0,0 +1,133 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use tokio::sync::Mutex;
+
+/// PIN attempts allowed per `accountx` in `LOCK_DURATION` *(a correct PIN clears them)*
+pub const MAX_FAILED_ATTEMPTS: u32 = 5;
+pub const LOCK_DURATION: Duration = Duration::from_secs(15 * 60);
+
+/// A PIN is 4–12 digits.
+pub fn is_valid_pin(pin: &str) -> bool {
+    (4..=12).contains(&pin.len()) && pin.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Hashing the PIN with a random salt *(argon2 `PHC` string)*.
+pub fn hash_pin(pin: &str) -> Result<String, String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(pin.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|error| error.to_string())
+}
+
+pub fn verify_pin(pin: &str, hash: &str) -> bool {
+    match PasswordHash::new(hash) {
+        Ok(parsed_hash) => Argon2::default()
+            .verify_password(pin.as_bytes(), &parsed_hash)
+            .is_ok(),
+        Err(_) => false,
+    }
+}
+
+struct FailedAttempts {
+    count: u32,
+    first: Instant,
+}
+
+/// Counting the PIN attempts per `accountx` against brute-forcing *(the correct ones are cleared)*.
+#[derive(Default)]
+pub struct PinAttempts {
+    failed: Mutex<HashMap<u32, FailedAttempts>>,
+}
+
+impl PinAttempts {
+    /// Counting an attempt before the PIN is verified, `false` if the `accountx` is locked.
+    ///
+    /// *(Counted and checked under one lock, so the parallel requests can't all pass before the
+    /// slow verifications fail.)*
+    pub async fn reserve(&self, accountx: u32) -> bool {
+        let mut failed = self.failed.lock().await;
+        let attempts = failed.entry(accountx).or_insert(FailedAttempts {
+            count: 0,
+            first: Instant::now(),
+        });
+        if attempts.first.elapsed() >= LOCK_DURATION {
+            attempts.count = 0;
+            attempts.first = Instant::now();
+        }
+        if attempts.count >= MAX_FAILED_ATTEMPTS {
+            return false;
+        }
+        attempts.count += 1;
+        true
+    }
+
+    pub async fn reset(&self, accountx: u32) {
+        self.failed.lock().await.remove(&accountx);
+    }
+
+    /// Removing the expired counters, returning the count of the removed ones.
+    pub async fn cleanup(&self) -> usize {
+        let mut failed = self.failed.lock().await;
+        let count_before = failed.len();
+        failed.retain(|_, attempts| attempts.first.elapsed() < LOCK_DURATION);
+        count_before - failed.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transfer_pin_hash_test() {
+        assert!(is_valid_pin("0123"));
+        assert!(!is_valid_pin("123"));
+        assert!(!is_valid_pin("12a4"));
+
+        let hash = hash_pin("0123").unwrap();
+        assert_ne!(hash, "0123");
+        assert!(verify_pin("0123", &hash));
+        assert!(!verify_pin("0124", &hash));
+        assert!(!verify_pin("0123", "not a hash"));
+    }
+
+    #[tokio::test]
+    async fn transfer_pin_attempts_test() {
+        let attempts = PinAttempts::default();
+        for _ in 0..MAX_FAILED_ATTEMPTS {
+            assert!(attempts.reserve(123456789).await);
+        }
+        assert!(!attempts.reserve(123456789).await);
+        assert!(attempts.reserve(987654321).await);
+
+        attempts.reset(123456789).await;
+        assert!(attempts.reserve(123456789).await);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn transfer_pin_parallel_attempts_test() {
+        let attempts = std::sync::Arc::new(PinAttempts::default());
+        let tasks: Vec<_> = (0..50)
+            .map(|_| {
+                let attempts = attempts.clone();
+                tokio::spawn(async move { attempts.reserve(123456789).await })
+            })
+            .collect();
+        let mut allowed = 0;
+        for task in tasks {
+            if task.await.unwrap() {
+                allowed += 1;
+            }
+        }
+        // only the limit gets to verify, however many arrive at once
+        assert_eq!(allowed, MAX_FAILED_ATTEMPTS);
+    }
+}